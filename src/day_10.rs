@@ -1,8 +1,6 @@
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::BufRead;
 use itertools::Itertools;
-use crate::util::Point;
+use crate::util::{CliArgs, Point};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 enum Pipe {
@@ -42,6 +40,63 @@ impl Pipe {
             Pipe::F90 => vec![right, below],
         }
     }
+
+    fn glyph(&self) -> char
+    {
+        match self {
+            Pipe::Vertical => '│',
+            Pipe::Horizontal => '─',
+            Pipe::L90 => '└',
+            Pipe::J90 => '┘',
+            Pipe::Seven90 => '┐',
+            Pipe::F90 => '┌',
+        }
+    }
+
+    fn heavy_glyph(&self) -> char
+    {
+        match self {
+            Pipe::Vertical => '┃',
+            Pipe::Horizontal => '━',
+            Pipe::L90 => '┗',
+            Pipe::J90 => '┛',
+            Pipe::Seven90 => '┓',
+            Pipe::F90 => '┏',
+        }
+    }
+}
+
+const ALL_PIPES: [Pipe; 6] = [Pipe::Vertical, Pipe::Horizontal, Pipe::L90, Pipe::J90, Pipe::Seven90, Pipe::F90];
+
+const DIRECTIONS: [Point; 4] = [
+    Point { x: 0, y: -1 },
+    Point { x: 0, y: 1 },
+    Point { x: -1, y: 0 },
+    Point { x: 1, y: 0 },
+];
+
+// How part two decides which tiles the loop encloses
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum EnclosedStrategy {
+    // Scan each row, flipping between inside and outside at every crossing of the loop
+    RowScan,
+    // Flood fill the outside of a 3x upscaled grid
+    FloodFill,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum StartPipeError {
+    NoStartingPosition,
+    NoClosedLoop,
+}
+
+impl std::fmt::Display for StartPipeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartPipeError::NoStartingPosition => write!(f, "The map has no starting position"),
+            StartPipeError::NoClosedLoop => write!(f, "No pipe at the starting position closes a loop"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -50,58 +105,75 @@ struct Marker {
     next: Point,
 }
 
+// Distance from S to each tile on the loop, going whichever way round is shorter
+#[derive(Debug, Clone)]
+struct LoopDistances {
+    ordered_loop: Vec<Point>,
+    distances: HashMap<Point, i64>,
+    max_distance: i64,
+    // One tile for an even length loop, or both ends of the middle edge for an odd one
+    farthest: Vec<Point>,
+}
+
+impl LoopDistances {
+    fn from_ordered_loop(ordered_loop: Vec<Point>) -> Self {
+        let len = ordered_loop.len() as i64;
+        let distances: HashMap<_, _> = ordered_loop.iter()
+            .enumerate()
+            .map(|(index, point)| (point.clone(), i64::min(index as i64, len - index as i64)))
+            .collect();
+        let max_distance = len / 2;
+        let farthest = ordered_loop.iter()
+            .filter(|point| distances[*point] == max_distance)
+            .cloned()
+            .collect();
+        Self {
+            ordered_loop,
+            distances,
+            max_distance,
+            farthest,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Map {
     pipes: HashMap<Point, Pipe>,
     starting_position: Point,
+    width: i64,
+    height: i64,
 }
 
 impl Map {
     fn longest_distance_from_start(&self) -> i64 {
-        let mut markers: (_, _) = self.pipes[&self.starting_position].connections()
-            .into_iter()
-            .map(|connection| Marker {
-                current: self.starting_position.clone(),
-                next: self.starting_position.clone() + connection,
-            })
-            .collect_tuple()
-            .unwrap();
-
-        let mut steps = 0;
-        loop {
-            steps += 1;
-
-            // Step Left
-            let next_connection = self.pipes[&markers.0.next].connections()
-                .into_iter()
-                .map(|connection| markers.0.next.clone() + connection)
-                .filter(|position| position != &markers.0.current)
-                .exactly_one()
-                .unwrap();
-            // Check both locations in case we crossed-over
-            if next_connection == markers.1.current || next_connection == markers.1.next {
-                return steps;
-            }
-            markers.0 = Marker {
-                next: next_connection,
-                current: markers.0.next,
-            };
+        self.loop_distances().max_distance
+    }
 
-            // Step Right
-            let next_connection = self.pipes[&markers.1.next].connections()
+    // The loop in order, starting from S and leaving through its first connection
+    fn ordered_loop(&self) -> Vec<Point> {
+        let mut ordered = vec![self.starting_position.clone()];
+        let mut marker = Marker {
+            current: self.starting_position.clone(),
+            next: self.starting_position.clone() + self.pipes[&self.starting_position].connections()[0].clone(),
+        };
+        while marker.next != self.starting_position {
+            ordered.push(marker.next.clone());
+            let next_connection = self.pipes[&marker.next].connections()
                 .into_iter()
-                .map(|connection| markers.1.next.clone() + connection)
-                .filter(|position| position != &markers.1.current)
+                .map(|connection| marker.next.clone() + connection)
+                .filter(|position| position != &marker.current)
                 .exactly_one()
                 .unwrap();
-            if next_connection == markers.0.current {
-                return steps;
-            }
-            markers.1 = Marker {
+            marker = Marker {
+                current: marker.next,
                 next: next_connection,
-                current: markers.1.next,
             };
         }
+        ordered
+    }
+
+    fn loop_distances(&self) -> LoopDistances {
+        LoopDistances::from_ordered_loop(self.ordered_loop())
     }
 
     fn get_loop_points(&self) -> HashSet<Point> {
@@ -123,47 +195,140 @@ impl Map {
         points
     }
 
-    fn complete_starting_position(&mut self)
+    // Tries every pipe whose ends both meet a neighbour connecting back, in case S has more than
+    // two such neighbours, and keeps the first one that leads back round to S
+    fn complete_starting_position(&mut self) -> Result<(), StartPipeError>
     {
-        let above = Point {
-            x: 0,
-            y: -1,
-        };
-        let below = Point {
-            x: 0,
-            y: 1,
-        };
-        let left = Point {
-            x: -1,
-            y: 0,
-        };
-        let right = Point {
-            x: 1,
-            y: 0,
-        };
-        let surrounding_connected_pipes = vec![above.clone(), below.clone(), left.clone(), right.clone()].into_iter()
-            .filter(|relative| {
+        let pipe = ALL_PIPES.iter()
+            .filter(|pipe| pipe.connections().iter().all(|relative| {
                 let position = self.starting_position.clone() + relative.clone();
-                if let Some(pipe) = self.pipes.get(&position) {
-                    return pipe.connections().contains(&relative.inverted());
-                }
-                false
-            })
-            .collect_vec();
+                self.pipes.get(&position)
+                    .is_some_and(|pipe| pipe.connections().contains(&relative.inverted()))
+            }))
+            .find(|pipe| self.closes_loop(pipe))
+            .ok_or(StartPipeError::NoClosedLoop)?;
+        self.pipes.insert(self.starting_position.clone(), pipe.clone());
+        Ok(())
+    }
 
-        let pipe = match (&surrounding_connected_pipes[0], &surrounding_connected_pipes[1]) {
-            (x, y) if *x == above && *y == below => Pipe::Vertical,
-            (x, y) if *x == left && *y == right => Pipe::Horizontal,
-            (x, y) if *x == above && *y == left => Pipe::J90,
-            (x, y) if *x == above && *y == right => Pipe::L90,
-            (x, y) if *x == below && *y == left => Pipe::Seven90,
-            (x, y) if *x == below && *y == right => Pipe::F90,
-            _ => panic!("Unable to figure out starting pipes"),
+    // Whether leaving S through the pipe's first connection leads back into its second
+    fn closes_loop(&self, starting_pipe: &Pipe) -> bool
+    {
+        let connections = starting_pipe.connections();
+        let mut marker = Marker {
+            current: self.starting_position.clone(),
+            next: self.starting_position.clone() + connections[0].clone(),
         };
-        self.pipes.insert(self.starting_position.clone(), pipe);
+        // Every pipe can only be visited once on the way round
+        for _ in 0..=self.pipes.len() {
+            if marker.next == self.starting_position {
+                return marker.current == self.starting_position.clone() + connections[1].clone();
+            }
+            let Some(pipe) = self.pipes.get(&marker.next) else {
+                return false;
+            };
+            let came_from = marker.current.clone() - marker.next.clone();
+            if !pipe.connections().contains(&came_from) {
+                return false;
+            }
+            let next_connection = pipe.connections()
+                .into_iter()
+                .find(|connection| *connection != came_from)
+                .unwrap();
+            marker = Marker {
+                next: marker.next.clone() + next_connection,
+                current: marker.next,
+            };
+        }
+        false
     }
 
     fn count_enclosed_points(&self) -> i64
+    {
+        self.enclosed_points().len() as i64
+    }
+
+    fn enclosed_points_with(&self, strategy: EnclosedStrategy) -> HashSet<Point>
+    {
+        match strategy {
+            EnclosedStrategy::RowScan => self.enclosed_points(),
+            EnclosedStrategy::FloodFill => self.enclosed_points_flood_fill(),
+        }
+    }
+
+    // Each tile becomes a 3x3 block with the loop drawn through its middle, which opens up gaps
+    // between pipes that run alongside each other. Returns the loop's cells in the upscaled grid.
+    fn upscaled_loop(&self) -> HashSet<Point>
+    {
+        self.get_loop_points()
+            .into_iter()
+            .flat_map(|point| {
+                let centre = Point { x: point.x * 3 + 1, y: point.y * 3 + 1 };
+                let mut cells = vec![centre.clone()];
+                cells.extend(self.pipes[&point].connections()
+                    .into_iter()
+                    .map(|connection| centre.clone() + connection));
+                cells
+            })
+            .collect()
+    }
+
+    // Floods the upscaled grid from a border one cell wide all the way round it, so the outside
+    // is always connected, squeezing between pipes wherever there's a gap
+    fn flood_outside(&self, upscaled_loop: &HashSet<Point>) -> HashSet<Point>
+    {
+        let (min, max_x, max_y) = (-1, self.width * 3, self.height * 3);
+        let mut outside = HashSet::new();
+        let mut points_to_explore = vec![Point { x: min, y: min }];
+        while let Some(point) = points_to_explore.pop() {
+            if point.x < min || point.y < min || point.x > max_x || point.y > max_y {
+                continue;
+            }
+            if upscaled_loop.contains(&point) || outside.contains(&point) {
+                continue;
+            }
+            outside.insert(point.clone());
+            points_to_explore.extend(DIRECTIONS.iter().map(|direction| point.clone() + direction.clone()));
+        }
+        outside
+    }
+
+    fn enclosed_points_flood_fill(&self) -> HashSet<Point>
+    {
+        let loop_points = self.get_loop_points();
+        let outside = self.flood_outside(&self.upscaled_loop());
+        (0..self.height)
+            .cartesian_product(0..self.width)
+            .map(|(y, x)| Point { x, y })
+            .filter(|point| !loop_points.contains(point))
+            .filter(|point| !outside.contains(&Point { x: point.x * 3 + 1, y: point.y * 3 + 1 }))
+            .collect()
+    }
+
+    // The upscaled grid the flood fill runs on: # for the loop, O where the flood reached, and I
+    // for anything it couldn't
+    fn render_upscaled(&self) -> String
+    {
+        let upscaled_loop = self.upscaled_loop();
+        let outside = self.flood_outside(&upscaled_loop);
+        let mut result = String::new();
+        for y in 0..(self.height * 3) {
+            for x in 0..(self.width * 3) {
+                let point = Point { x, y };
+                if upscaled_loop.contains(&point) {
+                    result.push('#');
+                } else if outside.contains(&point) {
+                    result.push('O');
+                } else {
+                    result.push('I');
+                }
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    fn enclosed_points(&self) -> HashSet<Point>
     {
         let min_y = self.pipes.keys()
             .map(|p| p.y)
@@ -173,15 +338,20 @@ impl Map {
             .map(|p| p.y)
             .max()
             .unwrap();
-        dbg!(min_y..(max_y + 1))
-            .map(|y| self.count_enclosed_points_in_row(y))
-            .sum()
+        let loop_points = self.get_loop_points();
+        (min_y..(max_y + 1))
+            .flat_map(|y| self.enclosed_points_in_row(y, &loop_points))
+            .collect()
     }
-    fn count_enclosed_points_in_row(&self, row: i64) -> i64
+
+    fn enclosed_points_in_row(&self, row: i64, loop_points: &HashSet<Point>) -> Vec<Point>
     {
         let pipes_in_row = self.pipes.keys()
             .filter(|point| point.y == row)
             .collect_vec();
+        if pipes_in_row.is_empty() {
+            return Vec::new();
+        }
         let min_x = pipes_in_row.iter()
             .map(|p| p.x)
             .min()
@@ -190,13 +360,12 @@ impl Map {
             .map(|p| p.x)
             .max()
             .unwrap();
-        let loop_points = self.get_loop_points();
 
         let mut last_corner_seen = None;
         let mut inside_pipes = false;
-        let mut count = 0;
+        let mut enclosed = Vec::new();
         for x in min_x..(max_x + 1) {
-            let point = Point{ x, y: row as i64 };
+            let point = Point{ x, y: row };
             if loop_points.contains(&point) {
                 let pipe = self.pipes[&point].clone();
                 match pipe {
@@ -217,25 +386,94 @@ impl Map {
                         last_corner_seen = None;
                     }
                 }
-            } else {
-                if inside_pipes {
-                    count += 1;
-                }
+            } else if inside_pipes {
+                enclosed.push(point);
+            }
+        }
+
+        enclosed
+    }
+
+    // Draws the main loop with box-drawing characters, the start in heavy lines, and every other
+    // tile (junk pipes included) as I or O depending on whether the loop encloses it
+    fn render(&self, style: RenderStyle) -> String
+    {
+        let loop_points = self.get_loop_points();
+        let enclosed_points = self.enclosed_points();
+        let mut result = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let point = Point { x, y };
+                let tile = if loop_points.contains(&point) {
+                    let pipe = &self.pipes[&point];
+                    if point == self.starting_position {
+                        Tile::Start(pipe.heavy_glyph())
+                    } else {
+                        Tile::Loop(pipe.glyph())
+                    }
+                } else {
+                    let junk = self.pipes.get(&point).map(|pipe| pipe.glyph());
+                    Tile::Other { enclosed: enclosed_points.contains(&point), junk }
+                };
+                result += &tile.render(style);
             }
+            result.push('\n');
         }
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum RenderStyle {
+    Plain,
+    Ansi,
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_START: &str = "\x1b[1;33m";
+const ANSI_INSIDE: &str = "\x1b[32m";
 
-        count
+enum Tile {
+    Start(char),
+    Loop(char),
+    Other { enclosed: bool, junk: Option<char> },
+}
+
+impl Tile {
+    fn render(&self, style: RenderStyle) -> String
+    {
+        let marker = |enclosed: bool| if enclosed { 'I' } else { 'O' };
+        match (self, style) {
+            (Tile::Start(glyph), RenderStyle::Plain) => glyph.to_string(),
+            (Tile::Start(glyph), RenderStyle::Ansi) => format!("{}{}{}", ANSI_START, glyph, ANSI_RESET),
+            (Tile::Loop(glyph), _) => glyph.to_string(),
+            (Tile::Other { enclosed, .. }, RenderStyle::Plain) => marker(*enclosed).to_string(),
+            // Junk pipes stay visible in the terminal, just dimmed
+            (Tile::Other { enclosed, junk }, RenderStyle::Ansi) => {
+                let colour = if *enclosed { ANSI_INSIDE } else { "" };
+                let character = junk.unwrap_or(marker(*enclosed));
+                format!("{}{}{}{}", ANSI_DIM, colour, character, ANSI_RESET)
+            }
+        }
     }
 }
 
 fn parse_input(file_name: &str) -> Map
 {
-    let file = File::open(file_name).expect("Unable to open file");
-    let lines = std::io::BufReader::new(file).lines();
+    let file_contents = std::fs::read_to_string(file_name).expect("Unable to open file");
+    parse_map(&file_contents).expect("Unable to figure out starting pipe")
+}
+
+fn parse_map(contents: &str) -> Result<Map, StartPipeError>
+{
     let mut pipes = HashMap::new();
     let mut starting_position = None;
-    for (row, line) in lines.enumerate() {
-        let line = line.expect("Unable to read line");
+    let mut width = 0;
+    let mut height = 0;
+    for (row, line) in contents.lines().enumerate() {
+        width = i64::max(width, line.chars().count() as i64);
+        height = row as i64 + 1;
         for (column, character) in line.chars().enumerate() {
             let position = Point {
                 x: column as i64,
@@ -262,10 +500,12 @@ fn parse_input(file_name: &str) -> Map
     }
     let mut result = Map {
         pipes,
-        starting_position: starting_position.expect("Unable to find starting position")
+        starting_position: starting_position.ok_or(StartPipeError::NoStartingPosition)?,
+        width,
+        height,
     };
-    result.complete_starting_position();
-    result
+    result.complete_starting_position()?;
+    Ok(result)
 }
 
 fn part_one(file_name: &str) -> i64
@@ -279,10 +519,35 @@ fn part_two(file_name: &str) -> i64
     map.count_enclosed_points()
 }
 
+pub fn run(args: &CliArgs) -> Result<(), String>
+{
+    let file_name = args.positional(0).ok_or("Missing input file")?;
+    let strategy = match args.value("strategy").unwrap_or("row-scan") {
+        "row-scan" => EnclosedStrategy::RowScan,
+        "flood-fill" => EnclosedStrategy::FloodFill,
+        strategy => return Err(format!("Unknown strategy: {}", strategy)),
+    };
+    let file_contents = std::fs::read_to_string(file_name).map_err(|error| error.to_string())?;
+    let map = parse_map(&file_contents).map_err(|error| error.to_string())?;
+    let rendered = match args.value("render") {
+        None => None,
+        Some("plain") => Some(map.render(RenderStyle::Plain)),
+        Some("ansi") => Some(map.render(RenderStyle::Ansi)),
+        Some("upscaled") => Some(map.render_upscaled()),
+        Some(style) => return Err(format!("Unknown render style: {}", style)),
+    };
+    if let Some(rendered) = rendered {
+        print!("{}", rendered);
+    }
+    println!("{}", map.longest_distance_from_start());
+    println!("{}", map.enclosed_points_with(strategy).len());
+    Ok(())
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn test_example()
@@ -325,4 +590,134 @@ mod test {
         let result = part_two("inputs/day_10/input.txt");
         println!("{}", result);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_render_plain()
+    {
+        let map = parse_input("inputs/day_10/example_2.txt");
+        let expected = "\
+OOOOOOOOOOO
+O┏───────┐O
+O│┌─────┐│O
+O││OOOOO││O
+O││OOOOO││O
+O│└─┐O┌─┘│O
+O│II│O│II│O
+O└──┘O└──┘O
+OOOOOOOOOOO
+";
+        assert_eq!(map.render(RenderStyle::Plain), expected);
+    }
+
+    #[test]
+    fn test_render_plain_counts()
+    {
+        let map = parse_input("inputs/day_10/example_3.txt");
+        let rendered = map.render(RenderStyle::Plain);
+        assert_eq!(rendered.matches('I').count(), 8);
+        assert_eq!(rendered.lines().count() as i64, map.height);
+        assert!(rendered.lines().all(|line| line.chars().count() as i64 == map.width));
+    }
+
+    #[test]
+    fn test_render_ansi()
+    {
+        let map = parse_input("inputs/day_10/example_4.txt");
+        let rendered = map.render(RenderStyle::Ansi);
+        assert_eq!(rendered.matches(ANSI_START).count(), 1);
+        assert_eq!(rendered.matches(&format!("{}{}", ANSI_DIM, ANSI_INSIDE)).count(), 10);
+        assert!(rendered.contains(&format!("{}{}", ANSI_START, '┓')));
+    }
+
+    #[test]
+    fn test_loop_distances()
+    {
+        let map = parse_input("inputs/day_10/example.txt");
+        let distances = map.loop_distances();
+        assert_eq!(distances.ordered_loop.len(), 16);
+        assert_eq!(distances.ordered_loop[0], map.starting_position);
+        assert_eq!(distances.distances[&map.starting_position], 0);
+        assert_eq!(distances.max_distance, 8);
+        assert_eq!(distances.farthest, vec![Point { x: 4, y: 2 }]);
+        assert_eq!(distances.distances.len(), map.get_loop_points().len());
+    }
+
+    #[test]
+    fn test_ordered_loop_is_connected()
+    {
+        let map = parse_input("inputs/day_10/example_4.txt");
+        let ordered_loop = map.ordered_loop();
+        for (point, next) in ordered_loop.iter().circular_tuple_windows() {
+            let step = next.clone() - point.clone();
+            assert!(map.pipes[point].connections().contains(&step));
+        }
+    }
+
+    #[test]
+    fn test_odd_length_loop_distances()
+    {
+        let ordered_loop = (0..5)
+            .map(|x| Point { x, y: 0 })
+            .collect_vec();
+        let distances = LoopDistances::from_ordered_loop(ordered_loop);
+        assert_eq!(distances.max_distance, 2);
+        assert_eq!(distances.farthest, vec![Point { x: 2, y: 0 }, Point { x: 3, y: 0 }]);
+        let expected_distances = [0, 1, 2, 2, 1];
+        for (x, expected_distance) in expected_distances.iter().enumerate() {
+            assert_eq!(distances.distances[&Point { x: x as i64, y: 0 }], *expected_distance);
+        }
+    }
+
+    #[rstest]
+    // S in the corner of the grid
+    #[case("S-7\n|.|\nL-J", Pipe::F90, 8)]
+    #[case("F-7\n|.|\nL-S", Pipe::J90, 8)]
+    #[case("F-S\n|.|\nL-J", Pipe::Seven90, 8)]
+    // S on an edge, with pipes pointing at it from outside the loop too
+    #[case("-S-7\n.|.|\n.L-J", Pipe::F90, 8)]
+    // Four neighbours connect to S, but only the top left loop is complete
+    #[case("F-7..\n|.|..\nL-S-7\n..|.|\n..L-.", Pipe::J90, 8)]
+    // Two complete loops through S, the first pipe to close one wins
+    #[case("F-7..\n|.|..\nL-S-7\n..|.|\n..L-J", Pipe::J90, 8)]
+    fn test_starting_pipe(#[case] input: &str, #[case] expected_pipe: Pipe, #[case] expected_loop_length: usize)
+    {
+        let map = parse_map(input).unwrap();
+        assert_eq!(map.pipes[&map.starting_position], expected_pipe);
+        assert_eq!(map.ordered_loop().len(), expected_loop_length);
+    }
+
+    #[rstest]
+    #[case("-S-", StartPipeError::NoClosedLoop)]
+    #[case("S", StartPipeError::NoClosedLoop)]
+    #[case("F-7\n|.|\nL.S", StartPipeError::NoClosedLoop)]
+    #[case("F-7\n|.|\nL-J", StartPipeError::NoStartingPosition)]
+    fn test_starting_pipe_errors(#[case] input: &str, #[case] expected_error: StartPipeError)
+    {
+        assert_eq!(parse_map(input).err(), Some(expected_error));
+    }
+
+    #[rstest]
+    #[case("inputs/day_10/example.txt", 1)]
+    #[case("inputs/day_10/example_2.txt", 4)]
+    #[case("inputs/day_10/example_3.txt", 8)]
+    #[case("inputs/day_10/example_4.txt", 10)]
+    fn test_flood_fill_agrees_with_row_scan(#[case] file_name: &str, #[case] expected_count: usize)
+    {
+        let map = parse_input(file_name);
+        let flood_filled = map.enclosed_points_with(EnclosedStrategy::FloodFill);
+        assert_eq!(flood_filled.len() as i64, map.count_enclosed_points());
+        assert_eq!(flood_filled, map.enclosed_points_with(EnclosedStrategy::RowScan));
+        assert_eq!(flood_filled.len(), expected_count);
+    }
+
+    #[test]
+    fn test_render_upscaled_squeezes_between_pipes()
+    {
+        let map = parse_input("inputs/day_10/example_2.txt");
+        let rendered = map.render_upscaled().lines().map(|line| line.to_string()).collect_vec();
+        // The gap between the two vertical pipes in the middle of the bottom half is reachable
+        assert_eq!(&rendered[19][15..18], "OOO");
+        // The enclosed tile at (2, 6) isn't
+        assert_eq!(rendered[19].chars().nth(7), Some('I'));
+    }
+}
@@ -1,7 +1,7 @@
-use std::collections::HashSet;
-use std::io::BufRead;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use itertools::Itertools;
-use crate::util::Point;
+use crate::util::{CliArgs, Point};
 
 struct Map {
     points: HashSet<Point>,
@@ -18,72 +18,276 @@ fn parse_line(line: &str, row: usize) -> Vec<Point>
         .collect()
 }
 
-fn parse_input(file_name: &str) -> Map
+impl Map {
+    // Emptiness is judged over the whole grid, not just the span between the outermost galaxies
+    fn new(points: HashSet<Point>, width: i64, height: i64) -> Self
+    {
+        let columns: HashSet<_> = points.iter().map(|p| p.x).collect();
+        let rows: HashSet<_> = points.iter().map(|p| p.y).collect();
+        let empty_columns = (0..width)
+            .filter(|x| !columns.contains(x))
+            .collect();
+        let empty_rows = (0..height)
+            .filter(|y| !rows.contains(y))
+            .collect();
+        Map {
+            empty_columns,
+            empty_rows,
+            points,
+        }
+    }
+}
+
+fn parse_map(input: &str) -> Map
 {
-    let file = std::fs::File::open(file_name).expect("Unable to open file");
-    let lines = std::io::BufReader::new(file).lines();
-    let points: HashSet<_> = lines.map(|line| line.expect("Unable to read line"))
+    let lines: Vec<_> = input.lines().collect();
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) as i64;
+    let height = lines.len() as i64;
+    let points = lines.iter()
         .enumerate()
-        .flat_map(|(row, line)| parse_line(&line, row))
-        .collect();
-    let columns: HashSet<_> = points.iter().map(|p| p.x).collect();
-    let rows: HashSet<_> = points.iter().map(|p| p.y).collect();
-    let column_min = *columns.iter().min().unwrap();
-    let column_max = *columns.iter().max().unwrap();
-    let empty_columns = (column_min..column_max)
-        .filter(|x| !columns.contains(x))
+        .flat_map(|(row, line)| parse_line(line, row))
         .collect();
-    let rows_min = *rows.iter().min().unwrap();
-    let rows_max = *rows.iter().max().unwrap();
-    let empty_rows = (rows_min..rows_max)
-        .filter(|y| !rows.contains(y))
+    Map::new(points, width, height)
+}
+
+fn parse_input(file_name: &str) -> Map
+{
+    let input = std::fs::read_to_string(file_name).expect("Unable to read file");
+    parse_map(&input)
+}
+
+// empty_before[i] is the number of empty lines with an index below i, for i up to max_index
+fn empty_before(empty: &HashSet<i64>, max_index: i64) -> Vec<i64>
+{
+    let mut result = Vec::with_capacity(max_index as usize + 1);
+    let mut count = 0;
+    for index in 0..=max_index {
+        result.push(count);
+        if empty.contains(&index) {
+            count += 1;
+        }
+    }
+    result
+}
+
+// Sorted, the i-th value is larger than the i values before it and smaller than the n-i-1 after
+// it, so it contributes value * (i - (n - i - 1)) to the sum of all pairwise differences
+fn sum_of_pairwise_distances(mut values: Vec<i64>) -> i64
+{
+    values.sort_unstable();
+    let n = values.len() as i64;
+    values.iter()
+        .enumerate()
+        .map(|(i, value)| value * (2 * i as i64 - n + 1))
+        .sum()
+}
+
+// How many lines each empty row or column becomes, so 1 leaves the universe as is and 0 collapses
+// empty space entirely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Expansion {
+    row_factor: i64,
+    column_factor: i64,
+}
+
+impl Expansion {
+    fn uniform(factor: i64) -> Self
+    {
+        Expansion { row_factor: factor, column_factor: factor }
+    }
+}
+
+struct ExpandedUniverse {
+    // Keyed by the galaxy's position in the original input
+    galaxies: HashMap<Point, Point>,
+    total_distance: i64,
+}
+
+// Manhattan distances split into independent x and y sums, each done by sorting
+fn expand(map: &Map, expansion: Expansion) -> ExpandedUniverse
+{
+    let max_x = map.points.iter().map(|p| p.x).max().unwrap_or(0);
+    let max_y = map.points.iter().map(|p| p.y).max().unwrap_or(0);
+    let empty_columns_before = empty_before(&map.empty_columns, max_x);
+    let empty_rows_before = empty_before(&map.empty_rows, max_y);
+    let galaxies: HashMap<_, _> = map.points.iter()
+        .map(|p| {
+            let expanded = Point {
+                x: p.x + empty_columns_before[p.x as usize] * (expansion.column_factor - 1),
+                y: p.y + empty_rows_before[p.y as usize] * (expansion.row_factor - 1),
+            };
+            (p.clone(), expanded)
+        })
         .collect();
-    Map {
-        empty_columns,
-        empty_rows,
-        points,
+    let total_distance = sum_of_pairwise_distances(galaxies.values().map(|p| p.x).collect())
+        + sum_of_pairwise_distances(galaxies.values().map(|p| p.y).collect());
+    ExpandedUniverse { galaxies, total_distance }
+}
+
+fn manhattan_distance(a: &Point, b: &Point) -> i64
+{
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+// Galaxies are always reported by their position in the original input, first before second in
+// reading order
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GalaxyPair {
+    first: Point,
+    second: Point,
+    distance: i64,
+}
+
+impl ExpandedUniverse {
+    // (original, expanded) in reading order, which is also used to break ties between distances
+    fn galaxies_in_order(&self) -> Vec<(&Point, &Point)>
+    {
+        self.galaxies.iter()
+            .sorted_by_key(|(original, _expanded)| (original.y, original.x))
+            .collect()
+    }
+
+    fn distance(&self, a: &Point, b: &Point) -> Option<i64>
+    {
+        Some(manhattan_distance(self.galaxies.get(a)?, self.galaxies.get(b)?))
+    }
+
+    // Every other galaxy within max_distance of the given one, closest first
+    fn within(&self, galaxy: &Point, max_distance: i64) -> Vec<(Point, i64)>
+    {
+        let Some(expanded) = self.galaxies.get(galaxy) else {
+            return Vec::new();
+        };
+        self.galaxies_in_order()
+            .into_iter()
+            .filter(|(original, _)| *original != galaxy)
+            .map(|(original, other)| (original.clone(), manhattan_distance(expanded, other)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .sorted_by_key(|(_, distance)| *distance)
+            .collect()
+    }
+
+    // None if the galaxy isn't in the universe or is the only one in it
+    fn nearest(&self, galaxy: &Point) -> Option<(Point, i64)>
+    {
+        self.within(galaxy, i64::MAX).into_iter().next()
+    }
+
+    // Keeps only the k best pairs on a heap rather than collecting all n^2 of them
+    fn extreme_pairs<K: Ord>(&self, k: usize, key: impl Fn(i64) -> K) -> Vec<GalaxyPair>
+    {
+        let galaxies = self.galaxies_in_order();
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (i, j) in (0..galaxies.len()).tuple_combinations() {
+            heap.push((key(manhattan_distance(galaxies[i].1, galaxies[j].1)), i, j));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(_, i, j)| GalaxyPair {
+                first: galaxies[i].0.clone(),
+                second: galaxies[j].0.clone(),
+                distance: manhattan_distance(galaxies[i].1, galaxies[j].1),
+            })
+            .collect()
+    }
+
+    fn closest_pairs(&self, k: usize) -> Vec<GalaxyPair>
+    {
+        self.extreme_pairs(k, |distance| distance)
+    }
+
+    fn farthest_pairs(&self, k: usize) -> Vec<GalaxyPair>
+    {
+        self.extreme_pairs(k, Reverse)
     }
 }
 
 fn part_one(file_name: &str) -> i64
 {
-    let map = parse_input(file_name);
-    map.points.iter()
-        .combinations(2)
-        .map(|points| {
-            let base_difference = points[0].clone() - points[1].clone();
-            let empty_rows_crossed = (i64::min(points[0].y, points[1].y)..i64::max(points[0].y, points[1].y))
-                .filter(|row| map.empty_rows.contains(row))
-                .count() as i64;
-            let empty_columns_crossed = (i64::min(points[0].x, points[1].x)..i64::max(points[0].x, points[1].x))
-                .filter(|column| map.empty_columns.contains(column))
-                .count() as i64;
-            i64::abs(base_difference.x) + i64::abs(base_difference.y) + empty_rows_crossed + empty_columns_crossed
-        })
-        .sum()
+    part_two(file_name, 2)
 }
 
 fn part_two(file_name: &str, multiplier: i64) -> i64
 {
     let map = parse_input(file_name);
-    map.points.iter()
-        .combinations(2)
-        .map(|points| {
-            let base_difference = points[0].clone() - points[1].clone();
-            let empty_rows_crossed = (i64::min(points[0].y, points[1].y)..i64::max(points[0].y, points[1].y))
-                .filter(|row| map.empty_rows.contains(row))
-                .count() as i64;
-            let empty_columns_crossed = (i64::min(points[0].x, points[1].x)..i64::max(points[0].x, points[1].x))
-                .filter(|column| map.empty_columns.contains(column))
-                .count() as i64;
-            i64::abs(base_difference.x) + i64::abs(base_difference.y) + empty_rows_crossed * (multiplier - 1) + empty_columns_crossed * (multiplier - 1)
-        })
-        .sum()
+    expand(&map, Expansion::uniform(multiplier)).total_distance
+}
+
+fn parse_point(text: &str) -> Result<Point, String>
+{
+    let (x, y) = text.split_once(',').ok_or(format!("Expected a galaxy as x,y: {}", text))?;
+    let parse = |value: &str| value.trim().parse().map_err(|_| format!("Invalid coordinate: {}", value));
+    Ok(Point { x: parse(x)?, y: parse(y)? })
+}
+
+fn parse_number<T: std::str::FromStr>(text: Option<&str>, name: &str) -> Result<T, String>
+{
+    let text = text.ok_or(format!("Missing {}", name))?;
+    text.parse().map_err(|_| format!("Invalid {}: {}", name, text))
+}
+
+fn format_pair(pair: &GalaxyPair) -> String
+{
+    format!("{},{} {},{} {}", pair.first.x, pair.first.y, pair.second.x, pair.second.y, pair.distance)
+}
+
+pub fn run(args: &CliArgs) -> Result<(), String>
+{
+    let file_name = args.positional(0).ok_or("Missing input file")?;
+    let factor = match args.value("expansion") {
+        Some(factor) => parse_number(Some(factor), "expansion")?,
+        None => 2,
+    };
+    let expansion = Expansion {
+        row_factor: args.value("rows").map_or(Ok(factor), |rows| parse_number(Some(rows), "rows"))?,
+        column_factor: args.value("columns").map_or(Ok(factor), |columns| parse_number(Some(columns), "columns"))?,
+    };
+    let file_contents = std::fs::read_to_string(file_name).map_err(|error| error.to_string())?;
+    let universe = expand(&parse_map(&file_contents), expansion);
+    let missing_galaxy = |galaxy: &Point| format!("No galaxy at {},{}", galaxy.x, galaxy.y);
+    match args.positional(1) {
+        None => println!("{}", universe.total_distance),
+        Some("nearest") => {
+            let galaxy = parse_point(args.positional(2).ok_or("Missing galaxy")?)?;
+            if !universe.galaxies.contains_key(&galaxy) {
+                return Err(missing_galaxy(&galaxy));
+            }
+            let (nearest, distance) = universe.nearest(&galaxy)
+                .ok_or(format!("No other galaxies besides {},{}", galaxy.x, galaxy.y))?;
+            println!("{},{} {}", nearest.x, nearest.y, distance);
+        }
+        Some("within") => {
+            let galaxy = parse_point(args.positional(2).ok_or("Missing galaxy")?)?;
+            if !universe.galaxies.contains_key(&galaxy) {
+                return Err(missing_galaxy(&galaxy));
+            }
+            let max_distance = parse_number(args.positional(3), "distance")?;
+            for (other, distance) in universe.within(&galaxy, max_distance) {
+                println!("{},{} {}", other.x, other.y, distance);
+            }
+        }
+        Some("closest") => {
+            for pair in universe.closest_pairs(parse_number(args.positional(2), "pair count")?) {
+                println!("{}", format_pair(&pair));
+            }
+        }
+        Some("farthest") => {
+            for pair in universe.farthest_pairs(parse_number(args.positional(2), "pair count")?) {
+                println!("{}", format_pair(&pair));
+            }
+        }
+        Some(query) => return Err(format!("Unknown query: {}", query)),
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn test_example()
@@ -114,4 +318,192 @@ mod test {
         let result = part_two("inputs/day_11/input.txt", 1_000_000);
         println!("{}", result);
     }
+
+    // Deterministic pseudo-random galaxies spread over a mostly empty universe
+    fn scattered_galaxies(count: usize, size: i64) -> Map
+    {
+        let mut state: u64 = 12345;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) % size as u64) as i64
+        };
+        let mut points = HashSet::new();
+        while points.len() < count {
+            points.insert(Point { x: next(), y: next() });
+        }
+        Map::new(points, size, size)
+    }
+
+    fn brute_force_total_distance(map: &Map, expansion: Expansion) -> i64
+    {
+        map.points.iter()
+            .tuple_combinations()
+            .map(|(a, b)| {
+                let crossed = |from: i64, to: i64, empty: &HashSet<i64>| {
+                    (i64::min(from, to)..i64::max(from, to))
+                        .filter(|index| empty.contains(index))
+                        .count() as i64
+                };
+                (a.x - b.x).abs() + (a.y - b.y).abs()
+                    + crossed(a.x, b.x, &map.empty_columns) * (expansion.column_factor - 1)
+                    + crossed(a.y, b.y, &map.empty_rows) * (expansion.row_factor - 1)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_matches_brute_force()
+    {
+        let map = scattered_galaxies(100, 200);
+        for (row_factor, column_factor) in [(1, 1), (2, 2), (0, 0), (0, 10), (1_000_000, 3)] {
+            let expansion = Expansion { row_factor, column_factor };
+            assert_eq!(expand(&map, expansion).total_distance, brute_force_total_distance(&map, expansion));
+        }
+    }
+
+    // Around a tenth of a second even in a debug build, so the bound leaves plenty of headroom
+    #[test]
+    fn test_many_galaxies_is_fast()
+    {
+        let map = scattered_galaxies(10_000, 20_000);
+        let start = std::time::Instant::now();
+        expand(&map, Expansion::uniform(1_000_000));
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sum_of_pairwise_distances()
+    {
+        assert_eq!(sum_of_pairwise_distances(vec![3, 1, 6]), 2 + 5 + 3);
+        assert_eq!(sum_of_pairwise_distances(vec![]), 0);
+    }
+
+    #[test]
+    fn test_expanded_coordinates()
+    {
+        let map = parse_input("inputs/day_11/example.txt");
+        let universe = expand(&map, Expansion { row_factor: 10, column_factor: 2 });
+        assert_eq!(universe.galaxies[&Point { x: 3, y: 0 }], Point { x: 4, y: 0 });
+        assert_eq!(universe.galaxies[&Point { x: 7, y: 8 }], Point { x: 9, y: 26 });
+        assert_eq!(universe.galaxies[&Point { x: 4, y: 9 }], Point { x: 5, y: 27 });
+    }
+
+    #[test]
+    fn test_collapsed_universe()
+    {
+        let map = parse_input("inputs/day_11/example.txt");
+        let universe = expand(&map, Expansion::uniform(0));
+        assert_eq!(universe.galaxies[&Point { x: 3, y: 0 }], Point { x: 2, y: 0 });
+        assert_eq!(universe.galaxies[&Point { x: 4, y: 9 }], Point { x: 3, y: 7 });
+    }
+
+    #[rstest]
+    #[case(".#\n.#", Point { x: 1, y: 0 }, Point { x: 2, y: 0 })]
+    #[case("#.\n#.", Point { x: 0, y: 1 }, Point { x: 0, y: 1 })]
+    #[case("..\n.#", Point { x: 1, y: 1 }, Point { x: 2, y: 2 })]
+    #[case("#.\n..", Point { x: 0, y: 0 }, Point { x: 0, y: 0 })]
+    #[case("...\n.#.\n...", Point { x: 1, y: 1 }, Point { x: 2, y: 2 })]
+    fn test_expansion_at_edges(#[case] input: &str, #[case] galaxy: Point, #[case] expected: Point)
+    {
+        let map = parse_map(input);
+        let universe = expand(&map, Expansion::uniform(2));
+        assert_eq!(universe.galaxies[&galaxy], expected);
+    }
+
+    #[rstest]
+    #[case("#..\n...\n..#", vec![1], vec![1])]
+    #[case("..#\n#..\n...", vec![1], vec![2])]
+    #[case(".#.\n...\n.#.", vec![0, 2], vec![1])]
+    #[case("...\n...", vec![0, 1, 2], vec![0, 1])]
+    fn test_empty_lines(#[case] input: &str, #[case] empty_columns: Vec<i64>, #[case] empty_rows: Vec<i64>)
+    {
+        let map = parse_map(input);
+        assert_eq!(map.empty_columns, empty_columns.into_iter().collect());
+        assert_eq!(map.empty_rows, empty_rows.into_iter().collect());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("....\n....")]
+    #[case("..\n.#")]
+    fn test_universe_without_pairs(#[case] input: &str)
+    {
+        let map = parse_map(input);
+        let universe = expand(&map, Expansion::uniform(1_000_000));
+        assert_eq!(universe.galaxies.len(), map.points.len());
+        assert_eq!(universe.total_distance, 0);
+    }
+
+    fn brute_force_pairs(universe: &ExpandedUniverse) -> Vec<GalaxyPair>
+    {
+        universe.galaxies_in_order()
+            .into_iter()
+            .tuple_combinations()
+            .map(|((first, a), (second, b))| GalaxyPair {
+                first: first.clone(),
+                second: second.clone(),
+                distance: manhattan_distance(a, b),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nearest()
+    {
+        let universe = expand(&parse_input("inputs/day_11/example.txt"), Expansion::uniform(2));
+        assert_eq!(universe.nearest(&Point { x: 0, y: 9 }), Some((Point { x: 4, y: 9 }, 5)));
+        assert_eq!(universe.nearest(&Point { x: 1, y: 0 }), None);
+        let lonely = expand(&parse_map("#"), Expansion::uniform(2));
+        assert_eq!(lonely.nearest(&Point { x: 0, y: 0 }), None);
+    }
+
+    #[test]
+    fn test_distance()
+    {
+        let universe = expand(&parse_input("inputs/day_11/example.txt"), Expansion::uniform(2));
+        assert_eq!(universe.distance(&Point { x: 1, y: 5 }, &Point { x: 4, y: 9 }), Some(9));
+        assert_eq!(universe.distance(&Point { x: 3, y: 0 }, &Point { x: 7, y: 8 }), Some(15));
+        assert_eq!(universe.distance(&Point { x: 0, y: 2 }, &Point { x: 9, y: 6 }), Some(17));
+        assert_eq!(universe.distance(&Point { x: 0, y: 9 }, &Point { x: 4, y: 9 }), Some(5));
+        assert_eq!(universe.distance(&Point { x: 0, y: 9 }, &Point { x: 0, y: 0 }), None);
+    }
+
+    #[test]
+    fn test_within()
+    {
+        let universe = expand(&parse_input("inputs/day_11/example.txt"), Expansion::uniform(2));
+        let galaxy = Point { x: 7, y: 8 };
+        let expected: Vec<_> = brute_force_pairs(&universe)
+            .into_iter()
+            .filter(|pair| pair.first == galaxy || pair.second == galaxy)
+            .filter(|pair| pair.distance <= 10)
+            .map(|pair| (if pair.first == galaxy { pair.second } else { pair.first }, pair.distance))
+            .sorted_by_key(|(other, distance)| (*distance, other.y, other.x))
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(universe.within(&galaxy, 10), expected);
+        assert_eq!(universe.within(&galaxy, 0), vec![]);
+    }
+
+    #[rstest]
+    #[case(Expansion::uniform(2))]
+    #[case(Expansion::uniform(0))]
+    #[case(Expansion { row_factor: 100, column_factor: 1 })]
+    fn test_extreme_pairs(#[case] expansion: Expansion)
+    {
+        let universe = expand(&parse_input("inputs/day_11/example.txt"), expansion);
+        let pairs = brute_force_pairs(&universe);
+        let closest: Vec<_> = pairs.iter()
+            .cloned()
+            .sorted_by_key(|pair| pair.distance)
+            .collect();
+        let farthest: Vec<_> = pairs.iter()
+            .cloned()
+            .sorted_by_key(|pair| Reverse(pair.distance))
+            .collect();
+        for k in [0, 1, 5, 36, 100] {
+            assert_eq!(universe.closest_pairs(k), closest.iter().take(k).cloned().collect::<Vec<_>>());
+            assert_eq!(universe.farthest_pairs(k), farthest.iter().take(k).cloned().collect::<Vec<_>>());
+        }
+    }
 }
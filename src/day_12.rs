@@ -1,67 +1,232 @@
-use std::io::BufRead;
 use std::ops::Index;
 use std::str::FromStr;
-use cached::proc_macro::cached;
 use itertools::Itertools;
 use lazy_regex::{regex_captures};
+use crate::util::CliArgs;
 
-#[cached]
-fn possible_arrangements(line: String, mut arrangement: Vec<usize>) -> usize
-{
-    if line.len() < arrangement.iter().sum() {
-        return 0;
+struct ArrangementCounts<'a> {
+    record: &'a [u8],
+    groups: &'a [usize],
+    // Length of the run of cells from each position that could all be springs
+    possible_runs: Vec<usize>,
+    // ways[pos * (groups.len() + 1) + group] is the number of ways record[pos..] can hold groups[group..]
+    ways: Vec<u128>,
+}
+
+impl<'a> ArrangementCounts<'a> {
+    // Filled from the end of the record backwards, so a group starting at pos only needs to look up
+    // the entry just past its trailing gap. None if the total doesn't fit in a u128
+    fn new(record: &'a [u8], groups: &'a [usize]) -> Option<Self>
+    {
+        let n = record.len();
+        let mut possible_runs = vec![0; n + 1];
+        for pos in (0..n).rev() {
+            if record[pos] != b'.' {
+                possible_runs[pos] = possible_runs[pos + 1] + 1;
+            }
+        }
+        let mut counts = ArrangementCounts {
+            record,
+            groups,
+            possible_runs,
+            ways: Vec::new(),
+        };
+        // None where the count doesn't fit
+        let mut ways: Vec<Option<u128>> = vec![Some(0); (n + 1) * (groups.len() + 1)];
+        ways[counts.index(n, groups.len())] = Some(1);
+        for pos in (0..n).rev() {
+            for group in 0..=groups.len() {
+                let spring_ways = counts.spring_step(pos, group)
+                    .map_or(Some(0), |next| ways[counts.index(next, group + 1)]);
+                let gap_ways = if record[pos] == b'#' { Some(0) } else { ways[counts.index(pos + 1, group)] };
+                ways[counts.index(pos, group)] = spring_ways.zip(gap_ways)
+                    .and_then(|(spring_ways, gap_ways)| spring_ways.checked_add(gap_ways));
+            }
+        }
+        ways[0]?;
+        // Every state reachable from the start counts towards the total, so once that fits only
+        // unreachable states can have overflowed, and those are never looked at again
+        counts.ways = ways.into_iter()
+            .map(|count| count.unwrap_or(0))
+            .collect();
+        Some(counts)
     }
-    if arrangement.len() == 0 {
-        if line.contains("#") {
-            // Unexpected springs
-            return 0;
+
+    fn index(&self, pos: usize, group: usize) -> usize
+    {
+        pos * (self.groups.len() + 1) + group
+    }
+
+    fn total(&self) -> u128
+    {
+        self.ways[0]
+    }
+
+    // Where the record continues if the next group starts at pos, just past its trailing gap
+    fn spring_step(&self, pos: usize, group: usize) -> Option<usize>
+    {
+        let len = *self.groups.get(group)?;
+        let end = pos + len;
+        let fits = self.possible_runs[pos] >= len
+            && (end == self.record.len() || self.record[end] != b'#');
+        fits.then(|| usize::min(end + 1, self.record.len()))
+    }
+
+    // (ways if a group starts at pos, ways if pos is operational)
+    fn choices(&self, pos: usize, group: usize) -> (u128, u128)
+    {
+        let spring_ways = self.spring_step(pos, group)
+            .map_or(0, |next| self.ways[self.index(next, group + 1)]);
+        let gap_ways = if self.record[pos] == b'#' { 0 } else { self.ways[self.index(pos + 1, group)] };
+        (spring_ways, gap_ways)
+    }
+
+    // Resolves the record one choice at a time, using choose_spring(spring_ways, gap_ways) whenever
+    // both a group and a gap could come next
+    fn walk(&self, mut choose_spring: impl FnMut(u128, u128) -> bool) -> Option<String>
+    {
+        if self.total() == 0 {
+            return None;
         }
-        return 1
-    }
-
-    let middle_group_size = arrangement.remove(arrangement.len() / 2);
-    // +1 to ensure we're splitting at the same place
-    let (arrangement_head, arrangement_tail) = arrangement.split_at((arrangement.len() + 1) / 2);
-    let arrangement_head = arrangement_head.to_vec();
-    let arrangement_tail = arrangement_tail.to_vec();
-
-    let possible_spans = find_possible_spring_spans(line.clone(), middle_group_size);
-    let line_string = line.to_string();
-    possible_spans.into_iter()
-        .map(|(start, end)| {
-            let line_head = line_string[..start].to_string() + ".";
-            let line_tail = ".".to_string() + &line_string[end..];
-            let head_arrangements = possible_arrangements(line_head.clone(), arrangement_head.clone());
-            if head_arrangements == 0 {
-                return 0;
+        let mut result = String::with_capacity(self.record.len());
+        let (mut pos, mut group) = (0, 0);
+        while pos < self.record.len() {
+            let (spring_ways, gap_ways) = self.choices(pos, group);
+            if gap_ways == 0 || (spring_ways != 0 && choose_spring(spring_ways, gap_ways)) {
+                let next = self.spring_step(pos, group).expect("Group should fit");
+                result.extend(std::iter::repeat_n('#', self.groups[group]));
+                if next > pos + self.groups[group] {
+                    result.push('.');
+                }
+                pos = next;
+                group += 1;
+            } else {
+                result.push('.');
+                pos += 1;
             }
-            let tail_arrangements = possible_arrangements(line_tail.clone(), arrangement_tail.clone());
-            head_arrangements * tail_arrangements
-        }).sum()
-}
+        }
+        Some(result)
+    }
 
-#[cached]
-fn find_possible_spring_spans(line: String, len: usize) -> Vec<(usize, usize)>
-{
-    if (len + 2) > line.len() {
-        return Vec::new();
-    }
-    let mut result = Vec::new();
-    for start_index in 0..(line.len() - len - 1) {
-        let slice = &line[start_index..(start_index + len + 2)].chars().collect_vec();
-        if !(slice[0] == '.' || slice[0] == '?') {
-            continue;
+    // Lexicographic by byte value, so '#' sorts before '.'
+    fn kth(&self, mut k: u128) -> Option<String>
+    {
+        if k >= self.total() {
+            return None;
         }
-        if !(slice[len + 1] == '.' || slice[len + 1] == '?') {
-            continue;
+        self.walk(|spring_ways, _gap_ways| {
+            if k < spring_ways {
+                true
+            } else {
+                k -= spring_ways;
+                false
+            }
+        })
+    }
+
+    fn arrangements(&self) -> impl Iterator<Item = String> + '_
+    {
+        (0..self.total()).map(|k| self.kth(k).expect("k is within the total"))
+    }
+
+    // random(n) should return a uniformly chosen value in 0..n. Each choice is weighted by how many
+    // arrangements follow from it, so every complete arrangement is equally likely
+    fn sample(&self, mut random: impl FnMut(u128) -> u128) -> Option<String>
+    {
+        self.walk(|spring_ways, gap_ways| random(spring_ways + gap_ways) < spring_ways)
+    }
+
+    // For each cell, how many arrangements have a spring there. Counts the ways to reach each state
+    // from the start, so every group placement is weighted by (ways to get here) * (ways to finish)
+    fn spring_counts(&self) -> Vec<u128>
+    {
+        let n = self.record.len();
+        // Only states that can never finish can overflow here, and as their ways are 0 saturating
+        // doesn't change any of the counts
+        let mut reach = vec![0u128; self.ways.len()];
+        reach[0] = 1;
+        // Arrangements whose group starts or ends at each position
+        let mut starts = vec![0u128; n + 1];
+        let mut ends = vec![0u128; n + 1];
+        for pos in 0..n {
+            for group in 0..=self.groups.len() {
+                let ways_here = reach[self.index(pos, group)];
+                if ways_here == 0 {
+                    continue;
+                }
+                if self.record[pos] != b'#' {
+                    let next = self.index(pos + 1, group);
+                    reach[next] = reach[next].saturating_add(ways_here);
+                }
+                if let Some(next) = self.spring_step(pos, group) {
+                    let next = self.index(next, group + 1);
+                    reach[next] = reach[next].saturating_add(ways_here);
+                    let through = ways_here.saturating_mul(self.ways[next]);
+                    starts[pos] += through;
+                    ends[pos + self.groups[group]] += through;
+                }
+            }
         }
-        let middle = &slice[1..(len + 1)];
-        if !middle.iter().all(|c| *c == '#' || *c == '?') {
-            continue;
+        let mut running = 0;
+        (0..n)
+            .map(|pos| {
+                running = running - ends[pos] + starts[pos];
+                running
+            })
+            .collect()
+    }
+}
+
+struct CellDeductions {
+    springs: String,
+    groups: Vec<usize>,
+    total: u128,
+    spring_counts: Vec<u128>,
+}
+
+impl CellDeductions {
+    fn new(springs: &str, groups: &[usize]) -> Option<Self>
+    {
+        let counts = ArrangementCounts::new(springs.as_bytes(), groups)?;
+        Some(CellDeductions {
+            springs: springs.to_string(),
+            groups: groups.to_vec(),
+            total: counts.total(),
+            spring_counts: counts.spring_counts(),
+        })
+    }
+
+    // '#' or '.' when every arrangement agrees on the cell. Nothing is forced without arrangements
+    fn forced(&self, pos: usize) -> Option<char>
+    {
+        match self.spring_counts[pos] {
+            _ if self.total == 0 => None,
+            0 => Some('.'),
+            count if count == self.total => Some('#'),
+            _ => None,
         }
-        result.push((start_index, start_index + len + 2));
     }
-    return result;
+
+    fn deduced_line(&self) -> String
+    {
+        self.springs.chars()
+            .enumerate()
+            .map(|(pos, c)| self.forced(pos).unwrap_or(c))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for CellDeductions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", self.springs, self.groups.iter().join(","))?;
+        writeln!(f, "{} {} arrangements", self.deduced_line(), self.total)?;
+        write!(f, "springs per cell: {}", self.spring_counts.iter().join(" "))
+    }
+}
+
+fn count_arrangements(record: &[u8], groups: &[usize]) -> Option<u128>
+{
+    Some(ArrangementCounts::new(record, groups)?.total())
 }
 
 fn parse_line(line: &str) -> (String, Vec<usize>)
@@ -74,43 +239,130 @@ fn parse_line(line: &str) -> (String, Vec<usize>)
     (line.to_string(), arrangement)
 }
 
-fn parse_line_part_two(line: &str) -> (String, Vec<usize>)
+// Repeats the springs factor times joined by the separator, and the groups to match
+fn unfold((springs, groups): &(String, Vec<usize>), factor: usize, separator: char) -> (String, Vec<usize>)
 {
-    let (_full_match, line, arrangement) = regex_captures!(r#"([?#.]+) ([\d,]+)"#, line)
-        .expect("Unable top parse line");
-    let arrangement = arrangement.split(",")
-        .map(|number| usize::from_str(number).expect("Unable to parse number"))
-        .collect_vec();
-    let line = itertools::repeat_n(line, 5)
-        .join("?");
-    (line, arrangement.repeat(5))
+    let springs = itertools::repeat_n(springs.as_str(), factor)
+        .join(&separator.to_string());
+    (springs, groups.repeat(factor))
+}
+
+// None if the total, or the count for any line, doesn't fit in a u128
+fn total_arrangements(input: &str, factor: usize, separator: char) -> Option<u128>
+{
+    input.lines()
+        .map(|line| unfold(&parse_line(line), factor, separator))
+        .try_fold(0u128, |total, (line, arrangement)| {
+            total.checked_add(count_arrangements(line.as_bytes(), &arrangement)?)
+        })
+}
+
+fn part_one(file_name: &str) -> u128
+{
+    let file_contents = std::fs::read_to_string(file_name).expect("Unable to read file");
+    total_arrangements(&file_contents, 1, '?').expect("Arrangement count overflowed")
 }
 
-fn part_one(file_name: &str) -> usize
+fn part_two(file_name: &str) -> u128
 {
-    let file = std::fs::File::open(file_name).expect("Unable to open file");
-    let lines = std::io::BufReader::new(file).lines();
-    lines.map(|line| line.expect("Unable to get line"))
-        .map(|line| parse_line(&line))
-        .map(|(line, arrangement)| possible_arrangements(format!(".{}.", line), arrangement))
-        .sum()
+    let file_contents = std::fs::read_to_string(file_name).expect("Unable to read file");
+    total_arrangements(&file_contents, 5, '?').expect("Arrangement count overflowed")
 }
 
-fn part_two(file_name: &str) -> usize
+pub fn run(args: &CliArgs) -> Result<(), String>
 {
-    let file = std::fs::File::open(file_name).expect("Unable to open file");
-    let lines = std::io::BufReader::new(file).lines();
-    lines.map(|line| line.expect("Unable to get line"))
-        .map(|line| parse_line_part_two(&line))
-        .map(|(line, arrangement)| possible_arrangements(format!(".{}.", line), arrangement))
-        .sum()
+    let file_name = args.positional(0).ok_or("Missing input file")?;
+    let factor = match args.value("unfold") {
+        Some(factor) => factor.parse().map_err(|_| format!("Invalid unfold factor: {}", factor))?,
+        None => 1,
+    };
+    let separator = match args.value("separator") {
+        None => '?',
+        Some(separator) => match separator.chars().collect_vec()[..] {
+            [c @ ('?' | '#' | '.')] => c,
+            _ => return Err(format!("Separator should be one of ?, # or .: {}", separator)),
+        },
+    };
+    let contents = std::fs::read_to_string(file_name).map_err(|error| error.to_string())?;
+    let overflow = || "Arrangement count doesn't fit in a u128".to_string();
+    let total = total_arrangements(&contents, factor, separator).ok_or_else(overflow)?;
+    if args.flag("deduce") {
+        for line in contents.lines() {
+            let (springs, groups) = unfold(&parse_line(line), factor, separator);
+            println!("{}\n", CellDeductions::new(&springs, &groups).ok_or_else(overflow)?);
+        }
+    }
+    println!("{}", total);
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashMap;
+    use cached::proc_macro::cached;
     use rstest::rstest;
 
+    // The original divide and conquer solver, kept as a reference for count_arrangements
+    #[cached]
+    fn possible_arrangements(line: String, mut arrangement: Vec<usize>) -> usize
+    {
+        if line.len() < arrangement.iter().sum() {
+            return 0;
+        }
+        if arrangement.len() == 0 {
+            if line.contains("#") {
+                // Unexpected springs
+                return 0;
+            }
+            return 1
+        }
+
+        let middle_group_size = arrangement.remove(arrangement.len() / 2);
+        // +1 to ensure we're splitting at the same place
+        let (arrangement_head, arrangement_tail) = arrangement.split_at((arrangement.len() + 1) / 2);
+        let arrangement_head = arrangement_head.to_vec();
+        let arrangement_tail = arrangement_tail.to_vec();
+
+        let possible_spans = find_possible_spring_spans(line.clone(), middle_group_size);
+        let line_string = line.to_string();
+        possible_spans.into_iter()
+            .map(|(start, end)| {
+                let line_head = line_string[..start].to_string() + ".";
+                let line_tail = ".".to_string() + &line_string[end..];
+                let head_arrangements = possible_arrangements(line_head.clone(), arrangement_head.clone());
+                if head_arrangements == 0 {
+                    return 0;
+                }
+                let tail_arrangements = possible_arrangements(line_tail.clone(), arrangement_tail.clone());
+                head_arrangements * tail_arrangements
+            }).sum()
+    }
+
+    #[cached]
+    fn find_possible_spring_spans(line: String, len: usize) -> Vec<(usize, usize)>
+    {
+        if (len + 2) > line.len() {
+            return Vec::new();
+        }
+        let mut result = Vec::new();
+        for start_index in 0..(line.len() - len - 1) {
+            let slice = &line[start_index..(start_index + len + 2)].chars().collect_vec();
+            if !(slice[0] == '.' || slice[0] == '?') {
+                continue;
+            }
+            if !(slice[len + 1] == '.' || slice[len + 1] == '?') {
+                continue;
+            }
+            let middle = &slice[1..(len + 1)];
+            if !middle.iter().all(|c| *c == '#' || *c == '?') {
+                continue;
+            }
+            result.push((start_index, start_index + len + 2));
+        }
+        return result;
+    }
+
     #[rstest]
     #[case("???.###", vec![1,1,3], 1)]
     #[case(".??..??...?##.", vec![1,1,3], 4)]
@@ -121,6 +373,7 @@ mod test {
     #[case(".###??????????###.", vec![2, 1, 3], 0)]
     fn test_examples(#[case] line: &str, #[case] arrangement: Vec<usize>, #[case] expected_result: usize)
     {
+        assert_eq!(count_arrangements(line.as_bytes(), &arrangement), Some(expected_result as u128));
         assert_eq!(possible_arrangements(format!(".{}.", line), arrangement), expected_result)
     }
 
@@ -156,9 +409,8 @@ mod test {
     #[case("?###????????", vec![3, 2, 1], 506250)]
     fn test_examples_part_two(#[case] line: &str, #[case] arrangement: Vec<usize>, #[case] expected_result: usize)
     {
-        let line = itertools::repeat_n(line, 5)
-            .join("?");
-        let arrangement = arrangement.repeat(5);
+        let (line, arrangement) = unfold(&(line.to_string(), arrangement), 5, '?');
+        assert_eq!(count_arrangements(line.as_bytes(), &arrangement), Some(expected_result as u128));
         assert_eq!(possible_arrangements(format!(".{}.", line), arrangement), expected_result)
     }
 
@@ -175,4 +427,207 @@ mod test {
         let result = part_two("inputs/day_12/input.txt");
         println!("{}", result);
     }
-}
\ No newline at end of file
+
+    #[rstest]
+    #[case("", vec![], 1)]
+    #[case("", vec![1], 0)]
+    #[case("#", vec![], 0)]
+    #[case("?", vec![], 1)]
+    #[case("#", vec![1], 1)]
+    #[case("##", vec![1], 0)]
+    #[case("??", vec![1], 2)]
+    #[case("???", vec![1, 1], 1)]
+    fn test_small_records(#[case] line: &str, #[case] arrangement: Vec<usize>, #[case] expected_result: u128)
+    {
+        assert_eq!(count_arrangements(line.as_bytes(), &arrangement), Some(expected_result));
+    }
+
+    // Whether a fully resolved arrangement agrees with the record and has exactly the given groups
+    fn is_valid_arrangement(record: &str, groups: &[usize], arrangement: &str) -> bool
+    {
+        let agrees = record.len() == arrangement.len() && record.chars()
+            .zip(arrangement.chars())
+            .all(|(r, a)| a != '?' && (r == '?' || r == a));
+        let actual_groups = arrangement.split('.')
+            .filter(|group| !group.is_empty())
+            .map(|group| group.len())
+            .collect_vec();
+        agrees && actual_groups == groups
+    }
+
+    fn lcg(seed: u64) -> impl FnMut(u128) -> u128
+    {
+        let mut state = seed;
+        move |n| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as u128 % n
+        }
+    }
+
+    #[rstest]
+    #[case("???.###", vec![1,1,3], 1)]
+    #[case(".??..??...?##.", vec![1,1,3], 4)]
+    #[case("?#?#?#?#?#?#?#?", vec![1,3,1,6], 1)]
+    #[case("????.#...#...", vec![4,1,1], 1)]
+    #[case("????.######..#####.", vec![1,6,5], 4)]
+    #[case("?###????????", vec![3, 2, 1], 10)]
+    #[case(".###??????????###.", vec![2, 1, 3], 0)]
+    fn test_enumerate_arrangements(#[case] line: &str, #[case] arrangement: Vec<usize>, #[case] expected_result: u128)
+    {
+        let counts = ArrangementCounts::new(line.as_bytes(), &arrangement).unwrap();
+        let all = counts.arrangements().collect_vec();
+        assert_eq!(all.len() as u128, expected_result);
+        assert!(all.iter().all(|a| is_valid_arrangement(line, &arrangement, a)));
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(counts.kth(expected_result), None);
+    }
+
+    #[test]
+    fn test_kth_arrangement()
+    {
+        let counts = ArrangementCounts::new(b"?###????????", &[3, 2, 1]).unwrap();
+        assert_eq!(counts.kth(0).as_deref(), Some(".###.##.#..."));
+        assert_eq!(counts.kth(9).as_deref(), Some(".###....##.#"));
+    }
+
+    #[rstest]
+    #[case("???.###", vec![1,1,3], 1)]
+    #[case(".??..??...?##.", vec![1,1,3], 16384)]
+    #[case("?#?#?#?#?#?#?#?", vec![1,3,1,6], 1)]
+    #[case("????.#...#...", vec![4,1,1], 16)]
+    #[case("????.######..#####.", vec![1,6,5], 2500)]
+    #[case("?###????????", vec![3, 2, 1], 506250)]
+    fn test_kth_arrangement_part_two(#[case] line: &str, #[case] arrangement: Vec<usize>, #[case] expected_result: u128)
+    {
+        let (line, arrangement) = unfold(&(line.to_string(), arrangement), 5, '?');
+        let counts = ArrangementCounts::new(line.as_bytes(), &arrangement).unwrap();
+        assert_eq!(counts.total(), expected_result);
+        let first = counts.kth(0).unwrap();
+        let middle = counts.kth(expected_result / 2).unwrap();
+        let last = counts.kth(expected_result - 1).unwrap();
+        for arrangement_string in [&first, &middle, &last] {
+            assert!(is_valid_arrangement(&line, &arrangement, arrangement_string));
+        }
+        assert!(first <= middle && middle <= last);
+        assert_eq!(counts.kth(expected_result), None);
+    }
+
+    #[test]
+    fn test_sample_is_uniform()
+    {
+        let counts = ArrangementCounts::new(b"?###????????", &[3, 2, 1]).unwrap();
+        let all = counts.arrangements().collect_vec();
+        let mut random = lcg(42);
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for _ in 0..10_000 {
+            let sample = counts.sample(&mut random).unwrap();
+            *frequencies.entry(sample).or_default() += 1;
+        }
+        assert_eq!(frequencies.len(), all.len());
+        // Each of the 10 arrangements is expected 1000 times
+        assert!(frequencies.values().all(|frequency| (800..1200).contains(frequency)));
+    }
+
+    #[test]
+    fn test_sample_without_arrangements()
+    {
+        let counts = ArrangementCounts::new(b".###??????????###.", &[2, 1, 3]).unwrap();
+        assert_eq!(counts.sample(lcg(1)), None);
+        assert_eq!(counts.arrangements().count(), 0);
+    }
+
+    #[rstest]
+    #[case(".#", vec![1], 3, '?', ".#?.#?.#", vec![1, 1, 1])]
+    #[case("???.###", vec![1, 1, 3], 2, '.', "???.###.???.###", vec![1, 1, 3, 1, 1, 3])]
+    #[case("#", vec![1], 1, '?', "#", vec![1])]
+    #[case("#", vec![1], 0, '?', "", vec![])]
+    fn test_unfold(
+        #[case] springs: &str,
+        #[case] groups: Vec<usize>,
+        #[case] factor: usize,
+        #[case] separator: char,
+        #[case] expected_springs: &str,
+        #[case] expected_groups: Vec<usize>,
+    )
+    {
+        let (unfolded_springs, unfolded_groups) = unfold(&(springs.to_string(), groups), factor, separator);
+        assert_eq!(unfolded_springs, expected_springs);
+        assert_eq!(unfolded_groups, expected_groups);
+    }
+
+    #[test]
+    fn test_large_unfold_factor()
+    {
+        // A spring in each of the 100 copies of "??", which can't fit two, so 2^100 choices in all
+        let (springs, groups) = unfold(&("??".to_string(), vec![1]), 100, '.');
+        assert_eq!(count_arrangements(springs.as_bytes(), &groups), Some(2u128.pow(100)));
+    }
+
+    #[test]
+    fn test_unfold_factor_past_u128()
+    {
+        let (springs, groups) = unfold(&("??".to_string(), vec![1]), 200, '.');
+        assert_eq!(count_arrangements(springs.as_bytes(), &groups), None);
+        assert!(CellDeductions::new(&springs, &groups).is_none());
+        let input = "?? 1\n";
+        assert_eq!(total_arrangements(input, 127, '.'), Some(2u128.pow(127)));
+        assert_eq!(total_arrangements(input, 128, '.'), None);
+        // Each line fits on its own, but not once they're added up
+        let input = "?? 1\n?? 1\n";
+        assert_eq!(total_arrangements(input, 127, '.'), None);
+    }
+
+    #[rstest]
+    #[case("???.###", vec![1,1,3])]
+    #[case(".??..??...?##.", vec![1,1,3])]
+    #[case("?#?#?#?#?#?#?#?", vec![1,3,1,6])]
+    #[case("????.#...#...", vec![4,1,1])]
+    #[case("????.######..#####.", vec![1,6,5])]
+    #[case("?###????????", vec![3, 2, 1])]
+    #[case(".###??????????###.", vec![2, 1, 3])]
+    fn test_spring_counts_match_enumeration(#[case] line: &str, #[case] arrangement: Vec<usize>)
+    {
+        let counts = ArrangementCounts::new(line.as_bytes(), &arrangement).unwrap();
+        let expected = (0..line.len())
+            .map(|pos| counts.arrangements().filter(|a| a.as_bytes()[pos] == b'#').count() as u128)
+            .collect_vec();
+        assert_eq!(counts.spring_counts(), expected);
+    }
+
+    #[rstest]
+    #[case("???.###", vec![1,1,3], "#.#.###")]
+    #[case(".??..??...?##.", vec![1,1,3], ".??..??...###.")]
+    #[case("?###????????", vec![3, 2, 1], ".###.???????")]
+    #[case("????.######..#####.", vec![1,6,5], "????.######..#####.")]
+    #[case(".###??????????###.", vec![2, 1, 3], ".###??????????###.")]
+    fn test_deduced_line(#[case] line: &str, #[case] arrangement: Vec<usize>, #[case] expected: &str)
+    {
+        assert_eq!(CellDeductions::new(line, &arrangement).unwrap().deduced_line(), expected);
+    }
+
+    #[test]
+    fn test_deduction_report()
+    {
+        let deductions = CellDeductions::new("?###????????", &[3, 2, 1]).unwrap();
+        assert_eq!(deductions.spring_counts, vec![0, 10, 10, 10, 0, 4, 7, 5, 4, 3, 3, 4]);
+        assert_eq!(deductions.forced(0), Some('.'));
+        assert_eq!(deductions.forced(1), Some('#'));
+        assert_eq!(deductions.forced(5), None);
+        assert_eq!(
+            deductions.to_string(),
+            "?###???????? 3,2,1\n.###.??????? 10 arrangements\nsprings per cell: 0 10 10 10 0 4 7 5 4 3 3 4"
+        );
+    }
+
+    #[test]
+    fn test_spring_counts_when_unfolded()
+    {
+        let (line, arrangement) = unfold(&("?###????????".to_string(), vec![3, 2, 1]), 5, '?');
+        let deductions = CellDeductions::new(&line, &arrangement).unwrap();
+        assert_eq!(deductions.total, 506250);
+        assert!(deductions.spring_counts.iter().all(|count| *count <= deductions.total));
+        // Every arrangement has the same number of springs, so the counts add up to that many totals
+        let springs: u128 = deductions.spring_counts.iter().sum();
+        assert_eq!(springs, 506250 * 6 * 5);
+    }
+}
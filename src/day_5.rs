@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use itertools::{Itertools};
 use lazy_regex::{regex, regex_captures};
 use std::ops::Range;
@@ -23,17 +24,20 @@ fn parse_seeds(line: &str) -> Vec<i64> {
         .collect()
 }
 
-fn parse_transformation(line: &str) -> Transformation {
+// None when a number or either range end doesn't fit in an i64, which validate_almanac reports
+fn parse_transformation(line: &str) -> Option<Transformation> {
     let (_full_match, dest_range_start, source_range_start, range_length) =
         regex_captures!(r#"(\d+) (\d+) (\d+)"#, line).expect("Unable to parse mapping line");
-    let source_range_start = i64::from_str(source_range_start).expect("Unable to parse number");
-    let dest_range_start = i64::from_str(dest_range_start).expect("Unable to parse number");
-    let range_length = i64::from_str(range_length).expect("Unable to parse number");
+    let source_range_start = i64::from_str(source_range_start).ok()?;
+    let dest_range_start = i64::from_str(dest_range_start).ok()?;
+    let range_length = i64::from_str(range_length).ok()?;
+    let source_range_end = source_range_start.checked_add(range_length)?;
+    dest_range_start.checked_add(range_length)?;
 
-    Transformation {
-        source_range: (source_range_start..(source_range_start + range_length)),
+    Some(Transformation {
+        source_range: (source_range_start..source_range_end),
         offset: dest_range_start - source_range_start,
-    }
+    })
 }
 
 fn parse_map(lines: Vec<&str>) -> ((&str, &str), Vec<Transformation>) {
@@ -41,14 +45,14 @@ fn parse_map(lines: Vec<&str>) -> ((&str, &str), Vec<Transformation>) {
         .expect("Unable to parse mapping header");
     let transformations = lines[1..]
         .into_iter()
-        .map(|line| parse_transformation(*line))
+        .filter_map(|line| parse_transformation(*line))
         .collect_vec();
     ((from, to), transformations)
 }
 
 fn parse_input(input: &str) -> (Vec<i64>, HashMap<(&str, &str), Vec<Transformation>>)
 {
-    let mut sections = input.split("\r\n\r\n");
+    let mut sections = input.split("\n\n");
     let seed_values = parse_seeds(sections.next().unwrap());
     let mappings = sections.map(|section| section.lines().collect_vec())
         .map(parse_map)
@@ -58,7 +62,7 @@ fn parse_input(input: &str) -> (Vec<i64>, HashMap<(&str, &str), Vec<Transformati
 
 fn parse_input_part_two(input: &str) -> (HashSet<ValueRange>, HashMap<(&str, &str), Vec<Transformation>>)
 {
-    let mut sections = input.split("\r\n\r\n");
+    let mut sections = input.split("\n\n");
     let seed_values = parse_seeds(sections.next().unwrap());
     let seed_ranges = seed_values.into_iter()
         .chunks(2)
@@ -129,10 +133,126 @@ fn map_values(input: ValueRange, mappings: &Vec<Transformation>) -> HashSet<Valu
     result
 }
 
-fn part_one(file_name: &str) {
-    let file_contents = std::fs::read_to_string(file_name)
-        .expect("Unable to read file");
-    let (seeds, mappings) = parse_input(file_contents.as_str());
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum AlmanacIssueKind {
+    OverlappingSourceRange { other_line_number: usize },
+    ZeroLengthRange,
+    Overflow,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct AlmanacIssue {
+    header: String,
+    line_number: usize,
+    kind: AlmanacIssueKind,
+}
+
+impl Display for AlmanacIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            AlmanacIssueKind::OverlappingSourceRange { other_line_number } => write!(
+                f,
+                "{} (line {}): source range overlaps the one on line {}",
+                self.header, self.line_number, other_line_number
+            ),
+            AlmanacIssueKind::ZeroLengthRange => write!(
+                f,
+                "{} (line {}): range has zero length",
+                self.header, self.line_number
+            ),
+            AlmanacIssueKind::Overflow => write!(
+                f,
+                "{} (line {}): range end overflows",
+                self.header, self.line_number
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Strictness {
+    Lenient,
+    Strict,
+}
+
+fn validate_almanac(input: &str) -> Vec<AlmanacIssue> {
+    let mut issues = Vec::new();
+    let mut header = None;
+    let mut source_ranges: Vec<(usize, Range<i64>)> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        if regex_captures!(r#"^\w+\-to\-\w+ map:$"#, line.trim()).is_some() {
+            header = Some(line.trim().trim_end_matches(':').to_string());
+            source_ranges.clear();
+            continue;
+        }
+        let Some(header) = &header else {
+            // Still in the seeds section
+            continue;
+        };
+        let Some((_full_match, dest_range_start, source_range_start, range_length)) =
+            regex_captures!(r#"^(\d+) (\d+) (\d+)$"#, line.trim()) else {
+            continue;
+        };
+
+        let mut report = |kind| issues.push(AlmanacIssue {
+            header: header.clone(),
+            line_number,
+            kind,
+        });
+        let (Ok(dest_range_start), Ok(source_range_start), Ok(range_length)) = (
+            i64::from_str(dest_range_start),
+            i64::from_str(source_range_start),
+            i64::from_str(range_length),
+        ) else {
+            report(AlmanacIssueKind::Overflow);
+            continue;
+        };
+        if range_length == 0 {
+            report(AlmanacIssueKind::ZeroLengthRange);
+            continue;
+        }
+        let (Some(source_range_end), Some(_dest_range_end)) = (
+            source_range_start.checked_add(range_length),
+            dest_range_start.checked_add(range_length),
+        ) else {
+            report(AlmanacIssueKind::Overflow);
+            continue;
+        };
+
+        let source_range = source_range_start..source_range_end;
+        for (other_line_number, other_range) in source_ranges.iter() {
+            if source_range.start < other_range.end && other_range.start < source_range.end {
+                report(AlmanacIssueKind::OverlappingSourceRange {
+                    other_line_number: *other_line_number,
+                });
+            }
+        }
+        source_ranges.push((line_number, source_range));
+    }
+    issues
+}
+
+// In lenient mode the issues come back as warnings: overlapping ranges resolve to the first one
+// listed, and overflowing lines are skipped
+fn check_almanac(input: &str, strictness: Strictness) -> Result<Vec<AlmanacIssue>, Vec<AlmanacIssue>> {
+    let issues = validate_almanac(input);
+    match strictness {
+        Strictness::Strict if !issues.is_empty() => Err(issues),
+        _ => Ok(issues),
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Solution {
+    lowest_location: i64,
+    warnings: Vec<AlmanacIssue>,
+}
+
+fn lowest_location_part_one(input: &str, strictness: Strictness) -> Result<Solution, Vec<AlmanacIssue>> {
+    let warnings = check_almanac(input, strictness)?;
+    let (seeds, mappings) = parse_input(input);
     let lowest_location = seeds
         .into_iter()
         .map(|seed| map_value(seed, &mappings[&("seed", "soil")]))
@@ -144,13 +264,12 @@ fn part_one(file_name: &str) {
         .map(|humidity| map_value(humidity, &mappings[&("humidity", "location")]))
         .min()
         .unwrap();
-    println!("{}", lowest_location);
+    Ok(Solution { lowest_location, warnings })
 }
 
-fn part_two(file_name: &str) {
-    let file_contents = std::fs::read_to_string(file_name)
-        .expect("Unable to read file");
-    let (seeds, mappings) = parse_input_part_two(file_contents.as_str());
+fn lowest_location_part_two(input: &str, strictness: Strictness) -> Result<Solution, Vec<AlmanacIssue>> {
+    let warnings = check_almanac(input, strictness)?;
+    let (seeds, mappings) = parse_input_part_two(input);
     let lowest_location = seeds
         .into_iter()
         .flat_map(|seed| map_values(seed, &mappings[&("seed", "soil")]))
@@ -163,7 +282,37 @@ fn part_two(file_name: &str) {
         .map(|range| range.inner.start)
         .min()
         .unwrap();
-    println!("{}", lowest_location);
+    Ok(Solution { lowest_location, warnings })
+}
+
+fn print_solution(solution: Result<Solution, Vec<AlmanacIssue>>) {
+    match solution {
+        Ok(solution) => {
+            for warning in solution.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            println!("{}", solution.lowest_location);
+        }
+        Err(issues) => {
+            for issue in issues {
+                eprintln!("error: {}", issue);
+            }
+        }
+    }
+}
+
+fn part_one(file_name: &str) {
+    let file_contents = std::fs::read_to_string(file_name)
+        .expect("Unable to read file")
+        .replace("\r\n", "\n");
+    print_solution(lowest_location_part_one(&file_contents, Strictness::Lenient));
+}
+
+fn part_two(file_name: &str) {
+    let file_contents = std::fs::read_to_string(file_name)
+        .expect("Unable to read file")
+        .replace("\r\n", "\n");
+    print_solution(lowest_location_part_two(&file_contents, Strictness::Lenient));
 }
 
 mod test {
@@ -287,4 +436,94 @@ mod test {
             ]),
         );
     }
+
+    const OVERLAPPING_ALMANAC: &str = "seeds: 79 14
+
+seed-to-soil map:
+50 98 2
+52 50 48
+10 60 5
+
+soil-to-location map:
+0 15 0
+";
+
+    #[test]
+    fn test_validate_example_almanac() {
+        let input = std::fs::read_to_string("inputs/day_5/example.txt")
+            .expect("Unable to read file")
+            .replace("\r\n", "\n");
+        assert_eq!(validate_almanac(&input), Vec::new());
+    }
+
+    #[test]
+    fn test_validate_overlapping_and_zero_length() {
+        assert_eq!(
+            validate_almanac(OVERLAPPING_ALMANAC),
+            vec![
+                AlmanacIssue {
+                    header: "seed-to-soil map".to_string(),
+                    line_number: 6,
+                    kind: AlmanacIssueKind::OverlappingSourceRange { other_line_number: 5 },
+                },
+                AlmanacIssue {
+                    header: "soil-to-location map".to_string(),
+                    line_number: 9,
+                    kind: AlmanacIssueKind::ZeroLengthRange,
+                },
+            ],
+        );
+    }
+
+    #[rstest]
+    #[case("9223372036854775800 0 10")]
+    #[case("0 9223372036854775800 10")]
+    #[case("0 0 99999999999999999999")]
+    fn test_validate_overflow(#[case] line: &str) {
+        let input = format!("seeds: 1\n\nseed-to-soil map:\n{}\n", line);
+        assert_eq!(
+            validate_almanac(&input),
+            vec![AlmanacIssue {
+                header: "seed-to-soil map".to_string(),
+                line_number: 4,
+                kind: AlmanacIssueKind::Overflow,
+            }],
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_ambiguous_almanac() {
+        assert!(check_almanac(OVERLAPPING_ALMANAC, Strictness::Strict).is_err());
+        assert_eq!(
+            check_almanac(OVERLAPPING_ALMANAC, Strictness::Lenient),
+            Ok(validate_almanac(OVERLAPPING_ALMANAC)),
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_solves_example() {
+        let input = std::fs::read_to_string("inputs/day_5/example.txt")
+            .expect("Unable to read file")
+            .replace("\r\n", "\n");
+        let solution = |lowest_location| Ok(Solution { lowest_location, warnings: Vec::new() });
+        assert_eq!(lowest_location_part_one(&input, Strictness::Strict), solution(35));
+        assert_eq!(lowest_location_part_two(&input, Strictness::Strict), solution(46));
+    }
+
+    #[rstest]
+    #[case("9223372036854775800 0 10")]
+    #[case("0 9223372036854775800 10")]
+    #[case("0 0 99999999999999999999")]
+    fn test_lenient_mode_skips_overflowing_lines(#[case] line: &str) {
+        let input = std::fs::read_to_string("inputs/day_5/example.txt")
+            .expect("Unable to read file")
+            .replace("\r\n", "\n")
+            .replace("seed-to-soil map:\n", &format!("seed-to-soil map:\n{}\n", line));
+        assert!(lowest_location_part_one(&input, Strictness::Strict).is_err());
+        let part_one = lowest_location_part_one(&input, Strictness::Lenient).unwrap();
+        assert_eq!(part_one.lowest_location, 35);
+        assert_eq!(part_one.warnings.len(), 1);
+        assert_eq!(part_one.warnings[0].kind, AlmanacIssueKind::Overflow);
+        assert_eq!(lowest_location_part_two(&input, Strictness::Lenient).unwrap().lowest_location, 46);
+    }
 }
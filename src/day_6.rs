@@ -14,20 +14,163 @@
 // eg: (3.6... or 10.6...) / 2
 // eg: 1.8... or 5.3...
 // eg: 2 -> 5 = 4 values
+//
+// Floats lose precision once time^2 passes 2^53, so the roots are found with an integer square
+// root instead and then nudged onto the exact boundary by checking the distance directly.
+// A hold that exactly ties the record doesn't win, so a perfect square discriminant excludes the roots.
 
 use itertools::Itertools;
 use std::fs::File;
 use std::io::BufRead;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
-fn num_winnable_values_for_race(time: f64, record: f64) -> u64 {
-    let sqrt_b_squared_minus_4ac = ((time * time) - (4.0 * record)).sqrt();
-    let min_winning_value = ((time - sqrt_b_squared_minus_4ac) / 2.0).floor() as u64 + 1;
-    let max_winning_value = ((time + sqrt_b_squared_minus_4ac) / 2.0).ceil() as u64 - 1;
-    max_winning_value + 1 - min_winning_value
+fn beats_record(hold: u128, time: u128, record: u128) -> bool {
+    hold * (time - hold) > record
 }
 
-fn parse_input(file_name: &str) -> Vec<(f64, f64)> {
+// Times and records are u64 so that, widened to u128, time squared always fits
+fn min_winning_hold(time: u64, record: u64) -> Option<u128> {
+    let (time, record) = (u128::from(time), u128::from(record));
+    let discriminant = (time * time).checked_sub(4 * record)?;
+    let mut hold = (time - discriminant.isqrt()) / 2;
+    // The integer root is within one of the real root, so at most a couple of steps either way
+    while hold > 0 && beats_record(hold - 1, time, record) {
+        hold -= 1;
+    }
+    while hold <= time / 2 && !beats_record(hold, time, record) {
+        hold += 1;
+    }
+    if hold > time / 2 {
+        return None;
+    }
+    Some(hold)
+}
+
+fn num_winnable_values_for_race(time: u64, record: u64) -> u128 {
+    match min_winning_hold(time, record) {
+        // Distance is symmetric around time / 2, so the max winning hold mirrors the min
+        Some(min_winning_value) => u128::from(time) - 2 * min_winning_value + 1,
+        None => 0,
+    }
+}
+
+// Generalised physics: speed = min(charge_rate * hold, max_speed), and holds shorter than
+// min_hold aren't allowed. Speed never decreases with hold and the remaining time always does,
+// so distance rises to a single peak and falls again - the winning holds form one interval
+// either side of it, which can be found by binary search without any square roots.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct RaceModel {
+    charge_rate: u128,
+    max_speed: Option<u128>,
+    min_hold: u128,
+}
+
+impl Default for RaceModel {
+    fn default() -> Self {
+        Self {
+            charge_rate: 1,
+            max_speed: None,
+            min_hold: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct RaceOutcome {
+    time: u128,
+    record: u128,
+    optimal_hold: Option<u128>,
+    winning_holds: Option<RangeInclusive<u128>>,
+}
+
+impl RaceOutcome {
+    fn num_winning_holds(&self) -> u128 {
+        match &self.winning_holds {
+            Some(holds) => holds.end() - holds.start() + 1,
+            None => 0,
+        }
+    }
+}
+
+impl RaceModel {
+    fn speed(&self, hold: u128) -> u128 {
+        let speed = self.charge_rate.saturating_mul(hold);
+        match self.max_speed {
+            Some(max_speed) => u128::min(speed, max_speed),
+            None => speed,
+        }
+    }
+
+    fn distance(&self, hold: u128, time: u128) -> u128 {
+        if hold < self.min_hold || hold > time {
+            return 0;
+        }
+        self.speed(hold).saturating_mul(time - hold)
+    }
+
+    fn optimal_hold(&self, time: u128) -> Option<u128> {
+        if self.min_hold > time {
+            return None;
+        }
+        // The peak is at the midpoint, unless the speed cap kicks in before then
+        let mut candidates = vec![time / 2, time.div_ceil(2)];
+        if let (Some(max_speed), true) = (self.max_speed, self.charge_rate > 0) {
+            candidates.push(max_speed / self.charge_rate);
+            candidates.push(max_speed.div_ceil(self.charge_rate));
+        }
+        candidates
+            .into_iter()
+            .map(|hold| hold.clamp(self.min_hold, time))
+            .sorted()
+            .max_by_key(|hold| (self.distance(*hold, time), std::cmp::Reverse(*hold)))
+    }
+
+    fn winning_holds(&self, time: u128, record: u128) -> Option<RangeInclusive<u128>> {
+        let optimal_hold = self.optimal_hold(time)?;
+        let beats_record = |hold| self.distance(hold, time) > record;
+        if !beats_record(optimal_hold) {
+            return None;
+        }
+
+        // Smallest winning hold in [min_hold, optimal_hold], where distance is rising
+        let (mut low, mut high) = (self.min_hold, optimal_hold);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if beats_record(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        let min_winning_hold = low;
+
+        // Largest winning hold in [optimal_hold, time], where distance is falling
+        let (mut low, mut high) = (optimal_hold, time);
+        while low < high {
+            let middle = low + (high - low).div_ceil(2);
+            if beats_record(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        let max_winning_hold = low;
+
+        Some(min_winning_hold..=max_winning_hold)
+    }
+
+    fn analyse_race(&self, time: u128, record: u128) -> RaceOutcome {
+        RaceOutcome {
+            time,
+            record,
+            optimal_hold: self.optimal_hold(time),
+            winning_holds: self.winning_holds(time, record),
+        }
+    }
+}
+
+fn parse_input(file_name: &str) -> Vec<(u64, u64)> {
     let file = File::open(file_name).expect("Unable to open file");
     let mut lines = std::io::BufReader::new(file)
         .lines()
@@ -37,20 +180,20 @@ fn parse_input(file_name: &str) -> Vec<(f64, f64)> {
         .expect("Could not fetch race times from input")
         .split_whitespace()
         .skip(1)
-        .map(|time| f64::from_str(time).expect("Unable to parse time"))
+        .map(|time| u64::from_str(time).expect("Unable to parse time"))
         .collect_vec();
     let records = lines
         .next()
         .expect("Could not fetch records from input")
         .split_whitespace()
         .skip(1)
-        .map(|record| f64::from_str(record).expect("Unable to parse record"))
+        .map(|record| u64::from_str(record).expect("Unable to parse record"))
         .collect_vec();
 
     races.into_iter().zip(records).collect()
 }
 
-fn parse_input_part_two(file_name: &str) -> (f64, f64) {
+fn parse_input_part_two(file_name: &str) -> (u64, u64) {
     let file = File::open(file_name).expect("Unable to open file");
     let mut lines = std::io::BufReader::new(file)
         .lines()
@@ -61,14 +204,14 @@ fn parse_input_part_two(file_name: &str) -> (f64, f64) {
         .split_whitespace()
         .skip(1)
         .join("");
-    let time = f64::from_str(&time).expect("Unable to parse time");
+    let time = u64::from_str(&time).expect("Unable to parse time");
     let record = lines
         .next()
         .expect("Could not fetch records from input")
         .split_whitespace()
         .skip(1)
         .join("");
-    let record = f64::from_str(&record).expect("Unable to parse record");
+    let record = u64::from_str(&record).expect("Unable to parse record");
 
     (time, record)
 }
@@ -82,6 +225,13 @@ fn part_one(file_name: &str) {
     println!("{}", result);
 }
 
+fn part_one_with_model(file_name: &str, model: &RaceModel) -> Vec<RaceOutcome> {
+    parse_input(file_name)
+        .into_iter()
+        .map(|(time, record)| model.analyse_race(u128::from(time), u128::from(record)))
+        .collect()
+}
+
 fn part_two(file_name: &str) {
     let (time, record) = parse_input_part_two(file_name);
     let result = num_winnable_values_for_race(time, record);
@@ -114,10 +264,106 @@ mod test {
     }
 
     #[rstest]
-    #[case(7.0, 9.0, 4)]
-    #[case(15.0, 40.0, 8)]
-    #[case(30.0, 200.0, 9)]
-    fn test_winnable_values(#[case] time: f64, #[case] record: f64, #[case] expected_count: u64) {
+    #[case(7, 9, 4)]
+    #[case(15, 40, 8)]
+    #[case(30, 200, 9)]
+    #[case(71530, 940200, 71503)]
+    fn test_winnable_values(#[case] time: u64, #[case] record: u64, #[case] expected_count: u128) {
         assert_eq!(num_winnable_values_for_race(time, record), expected_count);
     }
+
+    #[rstest]
+    // Perfect square discriminants, where the roots tie the record exactly
+    #[case(6, 8, 1)]
+    #[case(10, 21, 3)]
+    #[case(4, 4, 0)]
+    // Record can't be beaten at all
+    #[case(5, 100, 0)]
+    #[case(0, 0, 0)]
+    // Beyond f64's 2^53 integer precision
+    #[case(2_000_000_000, 1_000_000_000_000_000_000 - 1, 1)]
+    #[case(2_000_000_000, 1_000_000_000_000_000_000 - 9, 5)]
+    // Squaring the largest time still fits once widened
+    #[case(u64::MAX, 0, u64::MAX as u128 - 1)]
+    #[case(u64::MAX, u64::MAX, u64::MAX as u128 - 3)]
+    fn test_winnable_values_edge_cases(#[case] time: u64, #[case] record: u64, #[case] expected_count: u128) {
+        assert_eq!(num_winnable_values_for_race(time, record), expected_count);
+    }
+
+    #[test]
+    fn test_winnable_values_matches_brute_force() {
+        for time in 0..60u64 {
+            for record in 0..(time * time / 4 + 2) {
+                let expected_count = (0..=u128::from(time))
+                    .filter(|hold| beats_record(*hold, u128::from(time), u128::from(record)))
+                    .count() as u128;
+                assert_eq!(num_winnable_values_for_race(time, record), expected_count, "time {} record {}", time, record);
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(7, 9, 4)]
+    #[case(15, 40, 8)]
+    #[case(30, 200, 9)]
+    fn test_default_model_winning_holds(#[case] time: u128, #[case] record: u128, #[case] expected_count: u128) {
+        let outcome = RaceModel::default().analyse_race(time, record);
+        assert_eq!(outcome.num_winning_holds(), expected_count);
+    }
+
+    #[test]
+    fn test_default_model_example_intervals() {
+        let outcomes = part_one_with_model("inputs/day_6/example.txt", &RaceModel::default());
+        let intervals = outcomes.iter()
+            .map(|outcome| outcome.winning_holds.clone())
+            .collect_vec();
+        assert_eq!(intervals, vec![Some(2..=5), Some(4..=11), Some(11..=19)]);
+        let optimal_holds = outcomes.iter()
+            .map(|outcome| outcome.optimal_hold)
+            .collect_vec();
+        assert_eq!(optimal_holds, vec![Some(3), Some(7), Some(15)]);
+    }
+
+    #[rstest]
+    #[case(RaceModel { charge_rate: 2, max_speed: None, min_hold: 0 }, 7, 9, Some(1..=6), Some(3))]
+    #[case(RaceModel { charge_rate: 1, max_speed: Some(2), min_hold: 0 }, 7, 9, Some(2..=2), Some(2))]
+    #[case(RaceModel { charge_rate: 1, max_speed: None, min_hold: 4 }, 7, 9, Some(4..=5), Some(4))]
+    #[case(RaceModel { charge_rate: 1, max_speed: None, min_hold: 8 }, 7, 9, None, None)]
+    #[case(RaceModel { charge_rate: 0, max_speed: None, min_hold: 0 }, 7, 0, None, Some(3))]
+    #[case(RaceModel::default(), 5, 100, None, Some(2))]
+    fn test_model_variants(
+        #[case] model: RaceModel,
+        #[case] time: u128,
+        #[case] record: u128,
+        #[case] expected_holds: Option<RangeInclusive<u128>>,
+        #[case] expected_optimal_hold: Option<u128>,
+    ) {
+        let outcome = model.analyse_race(time, record);
+        assert_eq!(outcome.winning_holds, expected_holds);
+        assert_eq!(outcome.optimal_hold, expected_optimal_hold);
+    }
+
+    #[test]
+    fn test_model_matches_brute_force() {
+        let models = vec![
+            RaceModel::default(),
+            RaceModel { charge_rate: 3, max_speed: None, min_hold: 0 },
+            RaceModel { charge_rate: 2, max_speed: Some(7), min_hold: 0 },
+            RaceModel { charge_rate: 1, max_speed: Some(4), min_hold: 3 },
+        ];
+        for model in models {
+            for time in 0..30u128 {
+                for record in 0..60u128 {
+                    let winning_holds = (0..=time)
+                        .filter(|hold| model.distance(*hold, time) > record)
+                        .collect_vec();
+                    let expected_holds = match (winning_holds.first(), winning_holds.last()) {
+                        (Some(first), Some(last)) => Some(*first..=*last),
+                        _ => None,
+                    };
+                    assert_eq!(model.winning_holds(time, record), expected_holds, "{:?} time {} record {}", model, time, record);
+                }
+            }
+        }
+    }
 }
@@ -1,53 +1,41 @@
 use itertools::Itertools;
 use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::BufRead;
-use std::ops::AddAssign;
+use std::marker::PhantomData;
 use std::str::FromStr;
+use crate::util::CliArgs;
 
-fn cmp_hands(
-    hand: &str,
-    other: &str,
-    evaluate_hand_fn: fn(&str) -> HandType,
-    card_value_fn: fn(&char) -> u64,
-) -> Ordering {
-    let hand_type_ordering = evaluate_hand_fn(hand).cmp(&evaluate_hand_fn(&other));
-    if hand_type_ordering != Ordering::Equal {
-        return hand_type_ordering;
-    }
-
-    for (self_card, other_card) in hand.chars().zip(other.chars()) {
-        let self_card_value = card_value_fn(&self_card);
-        let other_card_value = card_value_fn(&other_card);
-        let card_ordering = self_card_value.cmp(&other_card_value);
-        if card_ordering != Ordering::Equal {
-            return card_ordering;
-        }
-    }
+const SUITS: &str = "cdhs";
+const MIN_STRAIGHT_LENGTH: usize = 5;
 
-    return Ordering::Equal;
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum HandError {
+    WrongLength(usize),
+    InvalidCard(char),
 }
 
-fn value_of_card_part_one(card: &char) -> u64 {
-    match card {
-        'A' => 20,
-        'K' => 19,
-        'Q' => 18,
-        'J' => 17,
-        'T' => 16,
-        c => u64::from_str(c.to_string().as_str()).expect("Unexpected card"),
-    }
+// Suits are optional and written as a lowercase letter after the rank, eg. "Ah"
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+struct Card {
+    rank: char,
+    suit: Option<char>,
 }
 
-fn value_of_card_part_two(card: &char) -> u64 {
-    match card {
-        'A' => 20,
-        'K' => 19,
-        'Q' => 18,
-        'T' => 16,
-        'J' => 0,
-        c => u64::from_str(c.to_string().as_str()).expect("Unexpected card"),
+fn parse_cards(hand: &str) -> Result<Vec<Card>, HandError> {
+    let mut cards: Vec<Card> = Vec::new();
+    for character in hand.chars() {
+        if SUITS.contains(character) {
+            match cards.last_mut() {
+                Some(card) if card.suit.is_none() => card.suit = Some(character),
+                _ => return Err(HandError::InvalidCard(character)),
+            }
+        } else {
+            cards.push(Card { rank: character, suit: None });
+        }
     }
+    Ok(cards)
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
@@ -55,105 +43,380 @@ pub enum HandType {
     HighCard,
     Pair,
     TwoPair,
+    ThreePair,
     ThreeOfAKind,
+    Straight,
+    Flush,
     FullHouse,
+    TwoTriples,
     FourOfAKind,
+    StraightFlush,
     FiveOfAKind,
+    SixOrMoreOfAKind,
 }
 
-fn evaluate_hand_part_one(hand: &str) -> HandType {
-    let counts = hand.chars().counts();
-    let max_count = counts.values().max().unwrap();
-    match max_count {
-        5 => HandType::FiveOfAKind,
-        4 => HandType::FourOfAKind,
-        3 => {
-            if counts.values().contains(&2) {
-                HandType::FullHouse
-            } else {
-                HandType::ThreeOfAKind
-            }
+// Counts of each distinct rank, largest first - eg. [3, 2] for a full house
+fn count_signature(ranks: impl Iterator<Item = char>) -> Vec<usize> {
+    ranks.counts()
+        .into_values()
+        .sorted()
+        .rev()
+        .collect()
+}
+
+fn classify_counts(signature: &[usize]) -> HandType {
+    match signature {
+        [count, ..] if *count >= 6 => HandType::SixOrMoreOfAKind,
+        [5, ..] => HandType::FiveOfAKind,
+        [4, ..] => HandType::FourOfAKind,
+        [3, 3, ..] => HandType::TwoTriples,
+        [3, 2, ..] => HandType::FullHouse,
+        [3, ..] => HandType::ThreeOfAKind,
+        [2, 2, 2, ..] => HandType::ThreePair,
+        [2, 2, ..] => HandType::TwoPair,
+        [2, ..] => HandType::Pair,
+        [1, ..] => HandType::HighCard,
+        _ => panic!("Unexpected hand counts"),
+    }
+}
+
+// A rule set decides how cards are ranked against each other and which cards are wild.
+// New variants only need to list their card order - everything else has a default.
+trait Rules {
+    // Every valid rank, weakest first
+    const CARD_ORDER: &'static str;
+    // Ranks that count as whichever rank makes the strongest hand
+    const WILDCARDS: &'static str = "";
+    // None allows hands of any size
+    const HAND_SIZE: Option<usize> = Some(5);
+    // Whether cards may carry a suit, which Camel Cards doesn't have
+    const SUITED: bool = false;
+
+    fn card_value(rank: &char) -> Option<u64> {
+        Self::CARD_ORDER.find(*rank).map(|index| index as u64)
+    }
+
+    fn is_wildcard(rank: &char) -> bool {
+        Self::WILDCARDS.contains(*rank)
+    }
+
+    // The rank the wildcards pretend to be: the most common other rank, highest first on ties
+    fn wildcard_target(cards: &[Card]) -> Option<char> {
+        cards.iter()
+            .map(|card| card.rank)
+            .filter(|rank| !Self::is_wildcard(rank))
+            .counts()
+            .into_iter()
+            .max_by_key(|(rank, count)| (*count, Self::card_value(rank)))
+            .map(|(rank, _count)| rank)
+    }
+
+    // With nothing but wildcards there's no other rank to copy, so they all group together under
+    // the first wildcard rank, whichever ranks they really are
+    fn count_signature(cards: &[Card]) -> Vec<usize> {
+        let target = Self::wildcard_target(cards)
+            .or(Self::WILDCARDS.chars().next());
+        count_signature(cards.iter().map(|card| match target {
+            Some(target) if Self::is_wildcard(&card.rank) => target,
+            _ => card.rank,
+        }))
+    }
+
+    fn evaluate_hand(cards: &[Card]) -> HandType {
+        classify_counts(&Self::count_signature(cards))
+    }
+
+    // Camel Cards breaks ties on the first card that differs, in the order they were dealt
+    fn tie_break_values(cards: &[Card]) -> Vec<u64> {
+        cards.iter()
+            .map(|card| Self::card_value(&card.rank).expect("Card validated on parse"))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct PartOneRules;
+
+impl Rules for PartOneRules {
+    const CARD_ORDER: &'static str = "23456789TJQKA";
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct PartTwoRules;
+
+impl Rules for PartTwoRules {
+    const CARD_ORDER: &'static str = "J23456789TQKA";
+    const WILDCARDS: &'static str = "J";
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct AcesLowRules;
+
+impl Rules for AcesLowRules {
+    const CARD_ORDER: &'static str = "A23456789TJQK";
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct DeucesAndJokersWildRules;
+
+impl Rules for DeucesAndJokersWildRules {
+    const CARD_ORDER: &'static str = "2J3456789TQKA";
+    const WILDCARDS: &'static str = "2J";
+}
+
+// Lifts the hand size restriction from any other rule set
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct AnySize<R>(PhantomData<R>);
+
+impl<R: Rules> Rules for AnySize<R> {
+    const CARD_ORDER: &'static str = R::CARD_ORDER;
+    const WILDCARDS: &'static str = R::WILDCARDS;
+    const HAND_SIZE: Option<usize> = None;
+    const SUITED: bool = R::SUITED;
+}
+
+// Real poker: straights (aces high or low) and flushes count, and ties are broken by the
+// highest group of cards rather than by the order the cards were dealt
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct PokerRules;
+
+impl PokerRules {
+    // Values from high to low as they'd be compared, with the ace moved to the bottom for a wheel (A2345)
+    fn straight_values(cards: &[Card]) -> Option<Vec<u64>> {
+        if cards.len() < MIN_STRAIGHT_LENGTH {
+            return None;
         }
-        2 => {
-            if counts.values().filter(|num| **num == 2).count() == 2 {
-                HandType::TwoPair
-            } else {
-                HandType::Pair
-            }
+        let values = cards.iter()
+            .map(|card| Self::card_value(&card.rank).expect("Card validated on parse"))
+            .sorted()
+            .rev()
+            .collect_vec();
+        let is_run = |values: &[u64]| values.iter().tuple_windows().all(|(a, b)| *a == b + 1);
+        if is_run(&values) {
+            return Some(values);
         }
-        1 => HandType::HighCard,
-        _ => panic!("Unexpected hand counts"),
+        let ace = Self::card_value(&'A').unwrap();
+        if values[0] == ace && is_run(&values[1..]) && *values.last().unwrap() == 0 {
+            let mut values = values[1..].to_vec();
+            values.push(ace);
+            return Some(values);
+        }
+        None
+    }
+
+    fn is_flush(cards: &[Card]) -> bool {
+        cards.len() >= MIN_STRAIGHT_LENGTH
+            && cards[0].suit.is_some()
+            && cards.iter().all(|card| card.suit == cards[0].suit)
     }
 }
 
-fn evaluate_hand_part_two(hand: &str) -> HandType {
-    // Count non-jokers
-    let mut counts = hand.chars().filter(|c| *c != 'J').counts();
-    let joker_counts = hand.chars().filter(|c| *c == 'J').count();
+impl Rules for PokerRules {
+    const CARD_ORDER: &'static str = "23456789TJQKA";
+    const HAND_SIZE: Option<usize> = None;
+    const SUITED: bool = true;
 
-    // Set jokers to match most common card (J if 5J hand)
-    let max_key = counts
-        .iter()
-        .max_by_key(|(_card, count)| **count)
-        .map(|(card, _count)| card.clone())
-        .unwrap_or('J');
-    counts.entry(max_key).or_insert(0).add_assign(joker_counts);
-    let max_count = counts[&max_key];
-
-    match max_count {
-        5 => HandType::FiveOfAKind,
-        4 => HandType::FourOfAKind,
-        3 => {
-            if counts.values().contains(&2) {
-                HandType::FullHouse
-            } else {
-                HandType::ThreeOfAKind
-            }
+    fn evaluate_hand(cards: &[Card]) -> HandType {
+        let by_counts = classify_counts(&Self::count_signature(cards));
+        let by_sequence = match (Self::straight_values(cards).is_some(), Self::is_flush(cards)) {
+            (true, true) => HandType::StraightFlush,
+            (false, true) => HandType::Flush,
+            (true, false) => HandType::Straight,
+            (false, false) => HandType::HighCard,
+        };
+        HandType::max(by_counts, by_sequence)
+    }
+
+    fn tie_break_values(cards: &[Card]) -> Vec<u64> {
+        if let Some(values) = Self::straight_values(cards) {
+            return values;
         }
-        2 => {
-            if counts.values().filter(|num| **num == 2).count() == 2 {
-                HandType::TwoPair
-            } else {
-                HandType::Pair
-            }
+        cards.iter()
+            .map(|card| Self::card_value(&card.rank).expect("Card validated on parse"))
+            .counts()
+            .into_iter()
+            .sorted_by_key(|(value, count)| (*count, *value))
+            .rev()
+            .flat_map(|(value, count)| itertools::repeat_n(value, count))
+            .collect()
+    }
+}
+
+// Parsed once up front so sorting only ever compares the precomputed key.
+// The count signature separates hands of the same type but different sizes, eg. [4, 4] over [4, 1].
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Hand<R> {
+    cards: Vec<Card>,
+    hand_type: HandType,
+    sort_key: (HandType, Vec<usize>, Vec<u64>),
+    rules: PhantomData<R>,
+}
+
+impl<R: Rules> FromStr for Hand<R> {
+    type Err = HandError;
+
+    fn from_str(hand: &str) -> Result<Self, Self::Err> {
+        let cards = parse_cards(hand)?;
+        if let Some(suit) = cards.iter().find_map(|card| card.suit).filter(|_| !R::SUITED) {
+            return Err(HandError::InvalidCard(suit));
         }
-        1 => HandType::HighCard,
-        _ => panic!("Unexpected hand counts"),
+        if cards.is_empty() || R::HAND_SIZE.is_some_and(|size| size != cards.len()) {
+            return Err(HandError::WrongLength(cards.len()));
+        }
+        if let Some(card) = cards.iter().find(|card| R::card_value(&card.rank).is_none()) {
+            return Err(HandError::InvalidCard(card.rank));
+        }
+        let hand_type = R::evaluate_hand(&cards);
+        let sort_key = (hand_type.clone(), R::count_signature(&cards), R::tie_break_values(&cards));
+        Ok(Self {
+            cards,
+            hand_type,
+            sort_key,
+            rules: PhantomData,
+        })
     }
 }
 
-fn parse_line(line: &str) -> (String, u64) {
+impl<R: Eq> PartialOrd for Hand<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: Eq> Ord for Hand<R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key.cmp(&other.sort_key)
+    }
+}
+
+fn parse_line<R: Rules>(line: &str) -> Result<(Hand<R>, u64), HandError> {
     let sections = line.split_whitespace().collect_vec();
-    let hand = sections[0].to_string();
+    let hand = Hand::from_str(sections[0])?;
     let bid = u64::from_str(sections[1]).expect("Unable to parse bid");
-    (hand, bid)
+    Ok((hand, bid))
 }
 
-fn part_one(file_name: &str) {
+// Why a hand ended up where it did, for debugging a wrong answer
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct HandExplanation {
+    hand: String,
+    hand_type: HandType,
+    // The rank any wildcards in the hand were counted as
+    wildcard_target: Option<char>,
+    // Position in the tie-break values (the dealt order for Camel Cards) that placed this hand
+    // above the one ranked just below it, if they were the same type
+    tie_break_index: Option<usize>,
+    rank: u64,
+    bid: u64,
+    winnings: u64,
+}
+
+impl Display for HandExplanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} {} {:?}", self.rank, self.hand, self.hand_type)?;
+        if let Some(target) = self.wildcard_target {
+            write!(f, " (wildcards as {})", target)?;
+        }
+        if let Some(index) = self.tie_break_index {
+            write!(f, " (tie broken at card {})", index)?;
+        }
+        write!(f, ": {} * {} = {}", self.bid, self.rank, self.winnings)
+    }
+}
+
+impl Display for Card {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.rank)?;
+        if let Some(suit) = self.suit {
+            write!(f, "{}", suit)?;
+        }
+        Ok(())
+    }
+}
+
+fn explain_winnings<R: Rules + Eq>(file_name: &str) -> Result<Vec<HandExplanation>, HandError> {
     let file = File::open(file_name).expect("Unable to open file");
-    let result: u64 = std::io::BufReader::new(file)
+    let hands: Vec<(Hand<R>, u64)> = std::io::BufReader::new(file)
         .lines()
         .map(|line| parse_line(&line.expect("Unable to get line")))
-        .sorted_by(|(hand, _bid), (other, _other_bid)| {
-            cmp_hands(hand, other, evaluate_hand_part_one, value_of_card_part_one)
-        })
+        .try_collect()?;
+    let sorted = hands.into_iter()
+        .sorted_by(|(hand, _bid), (other, _other_bid)| hand.cmp(other))
+        .collect_vec();
+
+    let explanations = sorted.iter()
         .enumerate()
-        .map(|(rank, (_hand, bid))| bid * (rank as u64 + 1))
+        .map(|(index, (hand, bid))| {
+            let wildcard_target = match hand.cards.iter().any(|card| R::is_wildcard(&card.rank)) {
+                true => Some(R::wildcard_target(&hand.cards).unwrap_or(hand.cards[0].rank)),
+                false => None,
+            };
+            let tie_break_index = index.checked_sub(1)
+                .map(|below| &sorted[below].0)
+                .filter(|below| (&below.sort_key.0, &below.sort_key.1) == (&hand.sort_key.0, &hand.sort_key.1))
+                .and_then(|below| {
+                    below.sort_key.2.iter()
+                        .zip(hand.sort_key.2.iter())
+                        .position(|(below_value, value)| below_value != value)
+                });
+            let rank = index as u64 + 1;
+            HandExplanation {
+                hand: hand.cards.iter().join(""),
+                hand_type: hand.hand_type.clone(),
+                wildcard_target,
+                tie_break_index,
+                rank,
+                bid: *bid,
+                winnings: bid * rank,
+            }
+        })
+        .collect();
+    Ok(explanations)
+}
+
+fn total_winnings<R: Rules + Eq>(file_name: &str) -> Result<u64, HandError> {
+    let result = explain_winnings::<R>(file_name)?
+        .iter()
+        .map(|explanation| explanation.winnings)
+        .sum();
+    Ok(result)
+}
+
+fn run_with_rules<R: Rules + Eq>(file_name: &str, explain: bool) -> Result<(), String> {
+    let explanations = explain_winnings::<R>(file_name)
+        .map_err(|error| format!("Invalid hand in input: {:?}", error))?;
+    if explain {
+        for explanation in explanations.iter() {
+            println!("{}", explanation);
+        }
+    }
+    let result: u64 = explanations.iter()
+        .map(|explanation| explanation.winnings)
         .sum();
     println!("{}", result);
+    Ok(())
+}
+
+pub fn run(args: &CliArgs) -> Result<(), String> {
+    let file_name = args.positional(0).ok_or("Missing input file")?;
+    let explain = args.flag("explain");
+    match args.value("rules").unwrap_or("part-one") {
+        "part-one" => run_with_rules::<PartOneRules>(file_name, explain),
+        "part-two" => run_with_rules::<PartTwoRules>(file_name, explain),
+        "aces-low" => run_with_rules::<AcesLowRules>(file_name, explain),
+        "deuces-wild" => run_with_rules::<DeucesAndJokersWildRules>(file_name, explain),
+        "poker" => run_with_rules::<PokerRules>(file_name, explain),
+        rules => Err(format!("Unknown rules: {}", rules)),
+    }
+}
+
+fn part_one(file_name: &str) {
+    let result = total_winnings::<PartOneRules>(file_name).expect("Invalid hand in input");
+    println!("{}", result);
 }
 
 fn part_two(file_name: &str) {
-    let file = File::open(file_name).expect("Unable to open file");
-    let result: u64 = std::io::BufReader::new(file)
-        .lines()
-        .map(|line| parse_line(&line.expect("Unable to get line")))
-        .sorted_by(|(hand, _bid), (other, _other_bid)| {
-            cmp_hands(hand, other, evaluate_hand_part_two, value_of_card_part_two)
-        })
-        .enumerate()
-        .map(|(rank, (_hand, bid))| bid * (rank as u64 + 1))
-        .sum();
+    let result = total_winnings::<PartTwoRules>(file_name).expect("Invalid hand in input");
     println!("{}", result);
 }
 
@@ -165,6 +428,7 @@ mod test {
     #[test]
     fn test_part_one_example() {
         part_one("inputs/day_7/example.txt");
+        assert_eq!(total_winnings::<PartOneRules>("inputs/day_7/example.txt"), Ok(6440));
     }
 
     #[test]
@@ -175,6 +439,7 @@ mod test {
     #[test]
     fn test_part_two_example() {
         part_two("inputs/day_7/example.txt");
+        assert_eq!(total_winnings::<PartTwoRules>("inputs/day_7/example.txt"), Ok(5905));
     }
 
     #[test]
@@ -191,16 +456,20 @@ mod test {
     #[case("A23A4", HandType::Pair)]
     #[case("23456", HandType::HighCard)]
     fn test_hand_evaluation(#[case] hand: &str, #[case] expected_hand_type: HandType) {
-        assert_eq!(evaluate_hand_part_one(hand), expected_hand_type);
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(PartOneRules::evaluate_hand(&cards), expected_hand_type);
+        // Without any jokers the part two rules classify hands identically
+        assert_eq!(PartTwoRules::evaluate_hand(&cards), expected_hand_type);
     }
 
     #[test]
     fn test_hand_ranking() {
-        let hands = vec!["32T3K", "KTJJT", "KK677", "T55J5", "QQQJA"];
+        let hands: Vec<Hand<PartOneRules>> = vec!["32T3K", "KTJJT", "KK677", "T55J5", "QQQJA"]
+            .into_iter()
+            .map(|hand| Hand::from_str(hand).unwrap())
+            .collect();
         let mut sorted = hands.clone();
-        sorted.sort_by(|hand, other| {
-            cmp_hands(hand, other, evaluate_hand_part_one, value_of_card_part_one)
-        });
+        sorted.sort();
         assert_eq!(hands, sorted);
     }
 
@@ -211,16 +480,189 @@ mod test {
     #[case("TTJ98", HandType::ThreeOfAKind)]
     #[case("A23J4", HandType::Pair)]
     fn test_hand_evaluation_part_two(#[case] hand: &str, #[case] expected_hand_type: HandType) {
-        assert_eq!(evaluate_hand_part_two(hand), expected_hand_type);
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(PartTwoRules::evaluate_hand(&cards), expected_hand_type);
+    }
+
+    #[rstest]
+    #[case("AAAAJ", HandType::FourOfAKind)]
+    #[case("AA8AJ", HandType::ThreeOfAKind)]
+    #[case("23J32", HandType::TwoPair)]
+    #[case("TTJ98", HandType::Pair)]
+    #[case("A23J4", HandType::HighCard)]
+    fn test_jokers_not_wild_in_part_one(#[case] hand: &str, #[case] expected_hand_type: HandType) {
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(PartOneRules::evaluate_hand(&cards), expected_hand_type);
+    }
+
+    #[rstest]
+    #[case("AAAAJ", 'A')]
+    #[case("23J32", '3')]
+    #[case("JJJJJ", 'J')]
+    fn test_wildcard_target(#[case] hand: &str, #[case] expected_target: char) {
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(PartTwoRules::wildcard_target(&cards).unwrap_or('J'), expected_target);
+    }
+
+    #[rstest]
+    #[case("AAAAJ", HandType::FiveOfAKind)]
+    #[case("AA8A2", HandType::FourOfAKind)]
+    #[case("23J32", HandType::FiveOfAKind)]
+    #[case("T2J98", HandType::ThreeOfAKind)]
+    #[case("A3J45", HandType::Pair)]
+    #[case("22JJJ", HandType::FiveOfAKind)]
+    #[case("2J2J2", HandType::FiveOfAKind)]
+    fn test_hand_evaluation_multiple_wildcards(#[case] hand: &str, #[case] expected_hand_type: HandType) {
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(DeucesAndJokersWildRules::evaluate_hand(&cards), expected_hand_type);
+    }
+
+    #[test]
+    fn test_hand_ranking_aces_low() {
+        let hands: Vec<Hand<AcesLowRules>> = vec!["A2345", "23456", "KQJT9", "AA234", "22345"]
+            .into_iter()
+            .map(|hand| Hand::from_str(hand).unwrap())
+            .collect();
+        let mut sorted = hands.clone();
+        sorted.sort();
+        assert_eq!(hands, sorted);
     }
 
     #[test]
     fn test_hand_ranking_part_two() {
-        let hands = vec!["32T3K", "KK677", "T55J5", "QQQJA", "KTJJT"];
+        let hands: Vec<Hand<PartTwoRules>> = vec!["32T3K", "KK677", "T55J5", "QQQJA", "KTJJT"]
+            .into_iter()
+            .map(|hand| Hand::from_str(hand).unwrap())
+            .collect();
         let mut sorted = hands.clone();
-        sorted.sort_by(|hand, other| {
-            cmp_hands(hand, other, evaluate_hand_part_two, value_of_card_part_two)
-        });
+        sorted.sort();
         assert_eq!(hands, sorted);
     }
+
+    #[rstest]
+    #[case("AAAA1", HandError::InvalidCard('1'))]
+    #[case("X2345", HandError::InvalidCard('X'))]
+    #[case("0KKKK", HandError::InvalidCard('0'))]
+    #[case("AAAA", HandError::WrongLength(4))]
+    #[case("AAAAAA", HandError::WrongLength(6))]
+    fn test_invalid_hands(#[case] hand: &str, #[case] expected_error: HandError) {
+        assert_eq!(Hand::<PartOneRules>::from_str(hand), Err(expected_error.clone()));
+        assert_eq!(Hand::<PartTwoRules>::from_str(hand), Err(expected_error));
+    }
+
+    #[test]
+    fn test_hand_parsing() {
+        let hand = Hand::<PartTwoRules>::from_str("KTJJT").unwrap();
+        assert_eq!(hand.cards.iter().map(|card| card.rank).collect_vec(), vec!['K', 'T', 'J', 'J', 'T']);
+        assert_eq!(hand.hand_type, HandType::FourOfAKind);
+        assert_eq!(hand.sort_key, (HandType::FourOfAKind, vec![4, 1], vec![11, 9, 0, 0, 9]));
+    }
+
+    #[test]
+    fn test_any_size_example() {
+        assert_eq!(total_winnings::<AnySize<PartOneRules>>("inputs/day_7/example.txt"), Ok(6440));
+        assert_eq!(total_winnings::<AnySize<PartTwoRules>>("inputs/day_7/example.txt"), Ok(5905));
+    }
+
+    #[test]
+    fn test_poker_example() {
+        assert_eq!(total_winnings::<PokerRules>("inputs/day_7/example.txt"), Ok(6440));
+    }
+
+    #[rstest]
+    #[case("AA", HandType::Pair)]
+    #[case("A", HandType::HighCard)]
+    #[case("AAKKQQ", HandType::ThreePair)]
+    #[case("AAAKKK", HandType::TwoTriples)]
+    #[case("AAAAKK", HandType::FourOfAKind)]
+    #[case("AAAAAAA", HandType::SixOrMoreOfAKind)]
+    #[case("23456789", HandType::HighCard)]
+    fn test_hand_evaluation_any_size(#[case] hand: &str, #[case] expected_hand_type: HandType) {
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(AnySize::<PartOneRules>::evaluate_hand(&cards), expected_hand_type);
+    }
+
+    #[test]
+    fn test_hand_ranking_any_size() {
+        let hands: Vec<Hand<AnySize<PartOneRules>>> = vec!["AK", "23456", "AKQJT9", "22", "AAKKQQ", "222", "KKKQQQ", "AAAA2", "22223333", "AAAAAAA"]
+            .into_iter()
+            .map(|hand| Hand::from_str(hand).unwrap())
+            .collect();
+        let mut sorted = hands.clone();
+        sorted.sort();
+        assert_eq!(hands, sorted);
+    }
+
+    #[rstest]
+    #[case("23456", HandType::Straight)]
+    #[case("TJQKA", HandType::Straight)]
+    #[case("A2345", HandType::Straight)]
+    #[case("QKA23", HandType::HighCard)]
+    #[case("2h7h9hJhAh", HandType::Flush)]
+    #[case("2h7h9hJhAs", HandType::HighCard)]
+    #[case("9sTsJsQsKs", HandType::StraightFlush)]
+    #[case("AsAhAdAcKs", HandType::FourOfAKind)]
+    #[case("KKK22", HandType::FullHouse)]
+    fn test_hand_evaluation_poker(#[case] hand: &str, #[case] expected_hand_type: HandType) {
+        let cards = parse_cards(hand).unwrap();
+        assert_eq!(PokerRules::evaluate_hand(&cards), expected_hand_type);
+    }
+
+    #[test]
+    fn test_hand_ranking_poker() {
+        let hands: Vec<Hand<PokerRules>> = vec!["AKQJ9", "3322K", "2233A", "AAA23", "A2345", "23456", "TJQKA", "2h3h4h5h7h", "KKK22", "AAAA2", "As2s3s4s5s"]
+            .into_iter()
+            .map(|hand| Hand::from_str(hand).unwrap())
+            .collect();
+        let mut sorted = hands.clone();
+        sorted.sort();
+        assert_eq!(hands, sorted);
+    }
+
+    #[test]
+    fn test_suits_only_allowed_in_poker() {
+        assert_eq!(Hand::<PartOneRules>::from_str("AhKKKK"), Err(HandError::InvalidCard('h')));
+        assert_eq!(Hand::<PartTwoRules>::from_str("AKKKKs"), Err(HandError::InvalidCard('s')));
+        assert_eq!(Hand::<AnySize<PartOneRules>>::from_str("2c3"), Err(HandError::InvalidCard('c')));
+        assert!(Hand::<PokerRules>::from_str("AhKhQhJhTh").is_ok());
+    }
+
+    #[rstest]
+    #[case("Ahh")]
+    #[case("hA")]
+    fn test_invalid_suits(#[case] hand: &str) {
+        assert_eq!(parse_cards(hand), Err(HandError::InvalidCard('h')));
+    }
+
+    #[test]
+    fn test_explain_example_part_two() {
+        let explanations = explain_winnings::<PartTwoRules>("inputs/day_7/example.txt").unwrap();
+        let lines = explanations.iter()
+            .map(|explanation| explanation.to_string())
+            .collect_vec();
+        assert_eq!(lines, vec![
+            "#1 32T3K Pair: 765 * 1 = 765",
+            "#2 KK677 TwoPair: 28 * 2 = 56",
+            "#3 T55J5 FourOfAKind (wildcards as 5): 684 * 3 = 2052",
+            "#4 QQQJA FourOfAKind (wildcards as Q) (tie broken at card 0): 483 * 4 = 1932",
+            "#5 KTJJT FourOfAKind (wildcards as T) (tie broken at card 0): 220 * 5 = 1100",
+        ]);
+        assert_eq!(explanations.iter().map(|explanation| explanation.winnings).sum::<u64>(), 5905);
+    }
+
+    #[test]
+    fn test_explain_example_part_one() {
+        let explanations = explain_winnings::<PartOneRules>("inputs/day_7/example.txt").unwrap();
+        let kk677 = &explanations[2];
+        assert_eq!(kk677.hand, "KK677");
+        assert_eq!(kk677.wildcard_target, None);
+        assert_eq!(kk677.tie_break_index, Some(1));
+    }
+
+    #[test]
+    fn test_explain_all_jokers() {
+        let cards = parse_cards("JJJJJ").unwrap();
+        assert_eq!(PartTwoRules::wildcard_target(&cards), None);
+        assert_eq!(PartTwoRules::evaluate_hand(&cards), HandType::FiveOfAKind);
+    }
 }
@@ -1,176 +1,423 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use itertools::Itertools;
 use lazy_regex::regex_captures;
+use regex::Regex;
+use crate::util::CliArgs;
 
+const DEFAULT_ALPHABET: &str = "LR";
+
+// Directions are stored as indexes into each node's connections, eg. L -> 0 and R -> 1
 #[derive(Debug, Clone)]
 struct Map {
-    directions: String,
+    alphabet: Vec<char>,
+    directions: Vec<usize>,
     nodes: HashMap<String, NodeConnections>
 }
 
 #[derive(Debug, Clone)]
 struct NodeConnections {
-    left: String,
-    right: String,
+    targets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum WalkError {
+    Unreachable,
+    MissingNode(String),
+    NoStartNodes,
+}
+
+impl Display for WalkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkError::Unreachable => write!(f, "The goal is never reached"),
+            WalkError::MissingNode(node) => write!(f, "Node {} is not in the network", node),
+            WalkError::NoStartNodes => write!(f, "No nodes match the start"),
+        }
+    }
+}
+
+impl Map {
+    fn step(&self, location: &str, direction: usize) -> Result<&String, WalkError> {
+        self.nodes.get(location)
+            .map(|connections| &connections.targets[direction])
+            .ok_or(WalkError::MissingNode(location.to_string()))
+    }
+
+    // Graphviz export. Start nodes are green and goal nodes red. When a path start is given, the
+    // edges walked before the ghost loops are drawn blue and the loop that find_cycle finds in orange.
+    fn to_dot(&self, start: &NodePredicate, goal: &NodePredicate, path_start: Option<&str>) -> Result<String, WalkError> {
+        let mut pre_cycle_edges = HashSet::new();
+        let mut cycle_edges = HashSet::new();
+        if let Some(path_start) = path_start {
+            let path = find_cycle(path_start, goal, self)?;
+            let mut location = path_start.to_string();
+            let directions = self.directions.iter().cycle();
+            for (steps, direction) in directions.take((path.cycle_start + path.cycle_length) as usize).enumerate() {
+                let edge = (location.clone(), *direction);
+                if (steps as i64) < path.cycle_start {
+                    pre_cycle_edges.insert(edge);
+                } else {
+                    cycle_edges.insert(edge);
+                }
+                location = self.step(&location, *direction)?.clone();
+            }
+        }
+
+        let mut lines = vec!["digraph network {".to_string()];
+        for node in self.nodes.keys().sorted() {
+            let colour = match (start.matches(node), goal.matches(node)) {
+                (true, true) => Some("gold"),
+                (true, false) => Some("palegreen"),
+                (false, true) => Some("lightcoral"),
+                (false, false) => None,
+            };
+            match colour {
+                Some(colour) => lines.push(format!("    \"{}\" [style=filled, fillcolor={}];", node, colour)),
+                None => lines.push(format!("    \"{}\";", node)),
+            }
+        }
+        for node in self.nodes.keys().sorted() {
+            for (direction, target) in self.nodes[node].targets.iter().enumerate().take(self.alphabet.len()) {
+                let edge = (node.clone(), direction);
+                let style = if cycle_edges.contains(&edge) {
+                    ", color=orange, penwidth=2"
+                } else if pre_cycle_edges.contains(&edge) {
+                    ", color=blue, penwidth=2"
+                } else {
+                    ""
+                };
+                lines.push(format!("    \"{}\" -> \"{}\" [label=\"{}\"{}];", node, target, self.alphabet[direction], style));
+            }
+        }
+        lines.push("}".to_string());
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+#[derive(Debug, Clone)]
+enum NodePredicate {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    Regex(Regex),
+}
+
+impl NodePredicate {
+    fn matches(&self, node: &str) -> bool {
+        match self {
+            NodePredicate::Exact(label) => node == label,
+            NodePredicate::Prefix(prefix) => node.starts_with(prefix.as_str()),
+            NodePredicate::Suffix(suffix) => node.ends_with(suffix.as_str()),
+            NodePredicate::Regex(regex) => regex.is_match(node),
+        }
+    }
 }
 
-fn parse_node(line: &str) -> (String, NodeConnections)
+// Written as kind:pattern, eg. "exact:AAA", "suffix:Z" or "regex:^1.Z$"
+impl FromStr for NodePredicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, pattern) = s.split_once(':')
+            .ok_or(format!("Expected kind:pattern, got {}", s))?;
+        match kind {
+            "exact" => Ok(NodePredicate::Exact(pattern.to_string())),
+            "prefix" => Ok(NodePredicate::Prefix(pattern.to_string())),
+            "suffix" => Ok(NodePredicate::Suffix(pattern.to_string())),
+            "regex" => Regex::new(pattern)
+                .map(NodePredicate::Regex)
+                .map_err(|error| error.to_string()),
+            _ => Err(format!("Unknown node predicate kind: {}", kind)),
+        }
+    }
+}
+
+fn parse_node(line: &str) -> Result<(String, NodeConnections), String>
 {
-    let (_full_match, node, left, right) = regex_captures!(r#"(\w{3}) = \((\w{3}), (\w{3})\)"#, line)
-        .expect("Unable to parse node");
-    (node.to_string(), NodeConnections { left: left.to_string(), right: right.to_string() })
+    let (_full_match, node, targets) = regex_captures!(r#"^(\w+) = \(([\w, ]+)\)$"#, line.trim())
+        .ok_or(format!("Unable to parse node: {}", line))?;
+    let targets = targets.split(',')
+        .map(|target| target.trim().to_string())
+        .collect();
+    Ok((node.to_string(), NodeConnections { targets }))
 }
 
+// For the puzzle inputs, which always use the default alphabet
 fn parse_input(contents: &str) -> Map
 {
-    let sections = contents.split("\n\n")
-        .collect_vec();
-    let directions = sections[0].trim().to_string();
-    let nodes = sections[1].lines()
+    parse_input_with_alphabet(contents, DEFAULT_ALPHABET).expect("Unable to parse map")
+}
+
+fn parse_input_with_alphabet(contents: &str, alphabet: &str) -> Result<Map, String>
+{
+    let (instructions, network) = contents.split_once("\n\n")
+        .ok_or("Expected directions and nodes separated by a blank line")?;
+    let alphabet = alphabet.chars().collect_vec();
+    let directions: Vec<_> = instructions.trim()
+        .chars()
+        .map(|direction| alphabet.iter()
+            .position(|letter| *letter == direction)
+            .ok_or(format!("Direction {} isn't in the alphabet {}", direction, alphabet.iter().join(""))))
+        .try_collect()?;
+    if directions.is_empty() {
+        return Err("No directions given".to_string());
+    }
+    let nodes: HashMap<_, _> = network.lines()
         .map(parse_node)
-        .collect();
-    Map {
+        .try_collect()?;
+    if let Some((node, connections)) = nodes.iter().find(|(_, connections)| connections.targets.len() < alphabet.len()) {
+        return Err(format!(
+            "Node {} has {} connections but there are {} directions",
+            node, connections.targets.len(), alphabet.len()
+        ));
+    }
+    Ok(Map {
+        alphabet,
         directions,
         nodes,
-    }
+    })
 }
 
-fn part_one(file_name: &str) -> u64
+fn part_one(file_name: &str) -> Result<u64, WalkError>
 {
     let file_contents = std::fs::read_to_string(file_name)
         .expect("Unable to read file")
         .replace("\r\n", "\n");
     let map = parse_input(&file_contents);
-    let mut steps = 0;
-    let mut location = "AAA".to_string();
-    let mut directions = map.directions.chars().cycle();
-    while location != "ZZZ" {
-        steps +=1;
-        let direction = directions.next().unwrap();
-        location = match direction {
-            'L' => map.nodes[&location].left.clone(),
-            'R' => map.nodes[&location].right.clone(),
-            _ => panic!("Unknown direction"),
-        }
-    }
-    return steps;
+    let start = NodePredicate::Exact("AAA".to_string());
+    let goal = NodePredicate::Exact("ZZZ".to_string());
+    steps_to_goal(&map, &start, &goal).map(|steps| steps as u64)
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct Cycle {
     offset: i64,
     length: i64,
 }
 
 impl Cycle {
-    fn merge(self, other: Self) -> Self {
-        if self.length > other.length {
-            // Ensure self is always the cycle with the lower length
-            return other.merge(self);
-        }
-        let mut desired_offset = self.offset - other.offset;
-        while desired_offset < 0 {
-            desired_offset += self.length;
+    // Every step that satisfies both cycles, as a single cycle, or None if they never line up
+    // (or only line up so rarely that the combined cycle no longer fits in an i64).
+    // The offset is the smallest non-negative step, found with the chinese remainder theorem.
+    fn combine(&self, other: &Self) -> Option<Self> {
+        let (gcd, inverse, _) = extended_gcd(self.length as i128, other.length as i128);
+        let difference = other.offset as i128 - self.offset as i128;
+        if difference % gcd != 0 {
+            return None;
         }
+        let length = self.length as i128 / gcd * other.length as i128;
+        let step = (difference / gcd * inverse).rem_euclid(other.length as i128 / gcd);
+        let offset = (self.offset as i128 + self.length as i128 * step).rem_euclid(length);
+        Some(Cycle {
+            offset: i64::try_from(offset).ok()?,
+            length: i64::try_from(length).ok()?,
+        })
+    }
 
-        let length_diff = other.length - self.length;
-        let mut cycles_needed_to_sync = 0;
-        let mut diff = cycles_needed_to_sync * length_diff;
-        while !(diff % self.length == desired_offset % self.length && diff >= desired_offset) {
-            cycles_needed_to_sync += 1;
-            diff = cycles_needed_to_sync * length_diff;
-        }
-        let combined_offset = other.offset + other.length * cycles_needed_to_sync;
-        let combined_length = num::integer::lcm(self.length, other.length);
+    // The first step at or after `from` in this cycle
+    fn first_from(&self, from: i64) -> i64 {
+        from + (self.offset - from).rem_euclid(self.length)
+    }
+}
 
-        Cycle {
-            offset: combined_offset,
-            length: combined_length,
+// Returns (gcd, x, y) such that a*x + b*y = gcd
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (gcd, x, y) = extended_gcd(b, a % b);
+    (gcd, y, x - (a / b) * y)
+}
+
+// Where a ghost lands on goal nodes: every step in pre_cycle_hits, then once the walk starts
+// repeating at cycle_start, every step congruent to one of the cycle_hits modulo cycle_length
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct GhostPath {
+    cycle_start: i64,
+    cycle_length: i64,
+    pre_cycle_hits: Vec<i64>,
+    cycle_hits: Vec<i64>,
+}
+
+impl GhostPath {
+    fn is_on_goal(&self, steps: i64) -> bool {
+        if steps < self.cycle_start {
+            return self.pre_cycle_hits.contains(&steps);
         }
+        let position_in_cycle = self.cycle_start + (steps - self.cycle_start) % self.cycle_length;
+        self.cycle_hits.contains(&position_in_cycle)
+    }
+
+    fn cycles(&self) -> Vec<Cycle> {
+        self.cycle_hits.iter()
+            .map(|hit| Cycle {
+                offset: *hit,
+                length: self.cycle_length,
+            })
+            .collect()
     }
 }
 
-fn find_cycle(starting_node: &str, map: &Map) -> Cycle
+// Walks until a (node, instruction index) state repeats, so it always terminates
+fn find_cycle(starting_node: &str, goal: &NodePredicate, map: &Map) -> Result<GhostPath, WalkError>
 {
     let mut explored_nodes = HashMap::new();
     let mut location = starting_node.to_string();
-    let mut directions = map.directions.chars()
+    let mut directions = map.directions.iter()
         .enumerate()
         .cycle();
     let (mut index, mut direction) = directions.next().unwrap();
-    let mut z_nodes = HashSet::new();
+    let mut goal_nodes = Vec::new();
     let mut steps = 0;
     while !explored_nodes.contains_key(&(location.clone(), index)) {
-        if location.ends_with("Z") {
-            z_nodes.insert(steps);
+        if goal.matches(&location) {
+            goal_nodes.push(steps);
         }
         explored_nodes.insert((location.clone(), index), steps);
-        location = match direction {
-            'L' => map.nodes[&location].left.clone(),
-            'R' => map.nodes[&location].right.clone(),
-            _ => panic!("Unknown direction"),
-        };
+        location = map.step(&location, *direction)?.clone();
         steps += 1;
         (index, direction) = directions.next().unwrap();
     }
     let cycle_start = explored_nodes[&(location.clone(), index)];
-    let first_z_node = *z_nodes.iter().min().unwrap();
+    let (pre_cycle_hits, cycle_hits) = goal_nodes.into_iter()
+        .partition(|step| *step < cycle_start);
+    Ok(GhostPath {
+        cycle_start,
+        cycle_length: steps - cycle_start,
+        pre_cycle_hits,
+        cycle_hits,
+    })
+}
+
+// The first step where every ghost is on a goal node at once, or None if that never happens
+fn earliest_common_goal_step(paths: &[GhostPath]) -> Option<i64>
+{
+    // Before every ghost has settled into its cycle, check each step directly
+    let settled_from = paths.iter()
+        .map(|path| path.cycle_start)
+        .max()?;
+    let early_hit = (0..settled_from)
+        .find(|steps| paths.iter().all(|path| path.is_on_goal(*steps)));
+    if early_hit.is_some() {
+        return early_hit;
+    }
 
-    // Assumption: Z nodes are spaced out evenly
-    // Holds true for the example which has multiple z nodes for the 22A path
-    // May not hold true for all inputs, but my input only had paths with a single Z node each
-    let cycle_length = (steps - cycle_start) / z_nodes.len();
-    Cycle {
-        offset: first_z_node as i64,
-        length: cycle_length as i64,
+    // After that each ghost is on a goal node whenever one of its cycles lines up, so combine
+    // every choice of cycle across the ghosts and take the earliest
+    let mut combined: HashSet<Cycle> = paths[0].cycles().into_iter().collect();
+    for path in paths[1..].iter() {
+        combined = combined.iter()
+            .cartesian_product(path.cycles())
+            .filter_map(|(cycle, other)| cycle.combine(&other))
+            .collect();
     }
+    combined.iter()
+        .map(|cycle| cycle.first_from(settled_from))
+        .min()
 }
 
-fn part_two(file_name: &str) -> i64
+// Walks a ghost from every node matching start at once, until they're all on goal nodes
+fn steps_to_goal(map: &Map, start: &NodePredicate, goal: &NodePredicate) -> Result<i64, WalkError>
+{
+    for predicate in [start, goal] {
+        if let NodePredicate::Exact(node) = predicate {
+            if !map.nodes.contains_key(node) {
+                return Err(WalkError::MissingNode(node.clone()));
+            }
+        }
+    }
+    let paths: Vec<_> = map.nodes.keys()
+        .filter(|node| start.matches(node))
+        .map(|node| find_cycle(node, goal, map))
+        .try_collect()?;
+    if paths.is_empty() {
+        return Err(WalkError::NoStartNodes);
+    }
+    earliest_common_goal_step(&paths).ok_or(WalkError::Unreachable)
+}
+
+fn part_two(file_name: &str) -> Result<i64, WalkError>
 {
     let file_contents = std::fs::read_to_string(file_name)
         .expect("Unable to read file")
         .replace("\r\n", "\n");
     let map = parse_input(&file_contents);
+    let start = NodePredicate::Suffix("A".to_string());
+    let goal = NodePredicate::Suffix("Z".to_string());
+    steps_to_goal(&map, &start, &goal)
+}
+
+pub fn run(args: &CliArgs) -> Result<(), String>
+{
+    let file_name = args.positional(0).ok_or("Missing input file")?;
+    let (default_start, default_goal) = match args.value("part").unwrap_or("one") {
+        "one" => ("exact:AAA", "exact:ZZZ"),
+        "two" => ("suffix:A", "suffix:Z"),
+        part => return Err(format!("Unknown part: {}", part)),
+    };
+    let start = NodePredicate::from_str(args.value("start").unwrap_or(default_start))?;
+    let goal = NodePredicate::from_str(args.value("goal").unwrap_or(default_goal))?;
+    let alphabet = args.value("alphabet").unwrap_or(DEFAULT_ALPHABET);
 
-    let full_cycle = map.nodes.keys()
-        .filter(|node| node.ends_with("A"))
-        .map(|node| find_cycle(node, &map))
-        .reduce(|cycle, other| cycle.merge(other));
-    full_cycle.unwrap().offset
+    let file_contents = std::fs::read_to_string(file_name)
+        .map_err(|error| error.to_string())?
+        .replace("\r\n", "\n");
+    let map = parse_input_with_alphabet(&file_contents, alphabet)?;
+    if let Some(dot_file_name) = args.value("dot") {
+        let path_start = args.value("highlight");
+        if path_start.is_some_and(|node| !map.nodes.contains_key(node)) {
+            return Err(format!("Unknown node to highlight: {}", path_start.unwrap()));
+        }
+        let dot = map.to_dot(&start, &goal, path_start)
+            .map_err(|error| error.to_string())?;
+        std::fs::write(dot_file_name, dot)
+            .map_err(|error| error.to_string())?;
+        return Ok(());
+    }
+    let steps = steps_to_goal(&map, &start, &goal)
+        .map_err(|error| error.to_string())?;
+    println!("{}", steps);
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rstest::rstest;
+
+    fn z_nodes() -> NodePredicate {
+        NodePredicate::Suffix("Z".to_string())
+    }
 
     #[test]
     fn test_part_one_example_1() {
         let result = part_one("inputs/day_8/example_1.txt");
-        println!("{}", result);
+        assert_eq!(result, Ok(2));
     }
 
     #[test]
     fn test_part_one_example_2() {
         let result = part_one("inputs/day_8/example_2.txt");
-        println!("{}", result);
+        assert_eq!(result, Ok(6));
     }
 
     #[test]
     fn test_part_one() {
-        let result = part_one("inputs/day_8/input.txt");
+        let result = part_one("inputs/day_8/input.txt").unwrap();
         println!("{}", result);
     }
 
     #[test]
     fn test_part_two_example_3() {
-        let result = part_two("inputs/day_8/example_3.txt");
+        let result = part_two("inputs/day_8/example_3.txt").unwrap();
         println!("{}", result);
     }
 
     #[test]
     fn test_part_two() {
-        let result = part_two("inputs/day_8/input.txt");
+        let result = part_two("inputs/day_8/input.txt").unwrap();
         println!("{}", result);
     }
 
@@ -181,14 +428,138 @@ mod test {
             .expect("Unable to read file")
             .replace("\r\n", "\n");
         let map = parse_input(&input);
-        let result = find_cycle("11A", &map);
-        assert_eq!(result, Cycle { offset: 2, length: 2});
-        let result = find_cycle("22A", &map);
-        assert_eq!(result, Cycle { offset: 3, length: 3 });
+        let result = find_cycle("11A", &z_nodes(), &map).unwrap();
+        assert_eq!(result.cycles(), vec![Cycle { offset: 2, length: 2}]);
+        let result = find_cycle("22A", &z_nodes(), &map).unwrap();
+        assert_eq!(result.cycles(), vec![Cycle { offset: 3, length: 6 }, Cycle { offset: 6, length: 6 }]);
+    }
+
+    #[test]
+    fn test_example_3_earliest_step()
+    {
+        assert_eq!(part_two("inputs/day_8/example_3.txt"), Ok(6));
     }
 
+    const EXAMPLE_3: &str = include_str!("../inputs/day_8/example_3.txt");
+
+    // 11A reaches Z nodes twice in its 4 step cycle, one step apart, so they aren't evenly spaced
+    const UNEVEN_Z_NODES: &str = "L
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (12Z, 12Z)
+12Z = (11C, 11C)
+11C = (11B, 11B)
+22A = (22B, 22B)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)";
+
     #[test]
-    fn test_cycle_merge()
+    fn test_uneven_z_nodes()
+    {
+        let map = parse_input(UNEVEN_Z_NODES);
+        let path = find_cycle("11A", &z_nodes(), &map).unwrap();
+        assert_eq!(path, GhostPath {
+            cycle_start: 1,
+            cycle_length: 4,
+            pre_cycle_hits: vec![],
+            cycle_hits: vec![2, 3],
+        });
+        let paths = vec![path, find_cycle("22A", &z_nodes(), &map).unwrap()];
+        assert_eq!(earliest_common_goal_step(&paths), Some(3));
+    }
+
+    // 33A only passes a Z node before it gets stuck looping on 33B
+    const PRE_CYCLE_Z_NODE: &str = "L
+
+33A = (33Z, 33Z)
+33Z = (33B, 33B)
+33B = (33B, 33B)
+44A = (44Z, 44Z)
+44Z = (44B, 44B)
+44B = (44Z, 44Z)
+22A = (22B, 22B)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)";
+
+    #[test]
+    fn test_pre_cycle_z_node()
+    {
+        let map = parse_input(PRE_CYCLE_Z_NODE);
+        let path = find_cycle("33A", &z_nodes(), &map).unwrap();
+        assert_eq!(path.pre_cycle_hits, vec![1]);
+        assert!(path.cycle_hits.is_empty());
+        let paths = vec![path.clone(), find_cycle("44A", &z_nodes(), &map).unwrap()];
+        assert_eq!(earliest_common_goal_step(&paths), Some(1));
+        let paths = vec![path, find_cycle("22A", &z_nodes(), &map).unwrap()];
+        assert_eq!(earliest_common_goal_step(&paths), None);
+    }
+
+    // Steps every ghost through the map together until they're all on a goal at once
+    fn brute_force_common_goal_step(map: &Map, starts: &[&str], limit: i64) -> Option<i64>
+    {
+        let mut locations = starts.iter().map(|start| start.to_string()).collect_vec();
+        for steps in 0..limit {
+            if locations.iter().all(|location| z_nodes().matches(location)) {
+                return Some(steps);
+            }
+            let direction = map.directions[steps as usize % map.directions.len()];
+            locations = locations.iter()
+                .map(|location| map.step(location, direction).unwrap().clone())
+                .collect();
+        }
+        None
+    }
+
+    #[rstest]
+    #[case(UNEVEN_Z_NODES, vec!["11A", "22A"])]
+    #[case(UNEVEN_Z_NODES, vec!["11A"])]
+    #[case(PRE_CYCLE_Z_NODE, vec!["33A", "44A"])]
+    #[case(PRE_CYCLE_Z_NODE, vec!["33A", "22A"])]
+    #[case(PRE_CYCLE_Z_NODE, vec!["44A", "22A"])]
+    #[case(EXAMPLE_3, vec!["11A", "22A"])]
+    fn test_matches_brute_force(#[case] input: &str, #[case] starts: Vec<&str>)
+    {
+        let map = parse_input(&input.replace("\r\n", "\n"));
+        let paths = starts.iter()
+            .map(|start| find_cycle(start, &z_nodes(), &map).unwrap())
+            .collect_vec();
+        assert_eq!(earliest_common_goal_step(&paths), brute_force_common_goal_step(&map, &starts, 1000));
+    }
+
+    #[test]
+    fn test_cycle_combine_incompatible()
+    {
+        let cycle_1 = Cycle {
+            offset: 1,
+            length: 4,
+        };
+        let cycle_2 = Cycle {
+            offset: 2,
+            length: 6,
+        };
+        assert_eq!(cycle_1.combine(&cycle_2), None);
+    }
+
+    #[test]
+    fn test_cycle_combine_overflow()
+    {
+        let cycle_1 = Cycle {
+            offset: 0,
+            length: 1 << 40,
+        };
+        let cycle_2 = Cycle {
+            offset: 1,
+            length: (1 << 40) + 1,
+        };
+        assert_eq!(cycle_1.combine(&cycle_2), None);
+        assert_eq!(cycle_2.combine(&cycle_1), None);
+    }
+
+    #[test]
+    fn test_cycle_combine()
     {
         let cycle_1 = Cycle {
             offset: 4,
@@ -198,12 +569,14 @@ mod test {
             offset: 2,
             length: 3,
         };
-        assert_eq!(cycle_1.clone().merge(cycle_2.clone()), Cycle { offset: 8, length: 6});
-        assert_eq!(cycle_2.merge(cycle_1), Cycle { offset: 8, length: 6});
+        let combined = Cycle { offset: 2, length: 6};
+        assert_eq!(cycle_1.combine(&cycle_2), Some(combined.clone()));
+        assert_eq!(cycle_2.combine(&cycle_1), Some(combined.clone()));
+        assert_eq!(combined.first_from(i64::max(cycle_1.offset, cycle_2.offset)), 8);
     }
 
     #[test]
-    fn test_cycle_merge_negative_offset()
+    fn test_cycle_combine_negative_offset()
     {
         let cycle_1 = Cycle {
             offset: 2,
@@ -213,12 +586,14 @@ mod test {
             offset: 4,
             length: 5,
         };
-        assert_eq!(cycle_1.clone().merge(cycle_2.clone()), Cycle { offset: 14, length: 15});
-        assert_eq!(cycle_2.merge(cycle_1), Cycle { offset: 14, length: 15});
+        let combined = Cycle { offset: 14, length: 15};
+        assert_eq!(cycle_1.combine(&cycle_2), Some(combined.clone()));
+        assert_eq!(cycle_2.combine(&cycle_1), Some(combined.clone()));
+        assert_eq!(combined.first_from(i64::max(cycle_1.offset, cycle_2.offset)), 14);
     }
 
     #[test]
-    fn test_cycle_merge_large_offset()
+    fn test_cycle_combine_large_offset()
     {
         let cycle_1 = Cycle {
             offset: 2,
@@ -228,7 +603,149 @@ mod test {
             offset: 20_001,
             length: 5,
         };
-        assert_eq!(cycle_1.clone().merge(cycle_2.clone()), Cycle { offset: 20_006, length: 15});
-        assert_eq!(cycle_2.merge(cycle_1), Cycle { offset: 20_006, length: 15});
+        let combined = Cycle { offset: 11, length: 15};
+        assert_eq!(cycle_1.combine(&cycle_2), Some(combined.clone()));
+        assert_eq!(cycle_2.combine(&cycle_1), Some(combined.clone()));
+        assert_eq!(combined.first_from(i64::max(cycle_1.offset, cycle_2.offset)), 20_006);
+    }
+
+    #[rstest]
+    #[case("exact:AAA", "AAA", true)]
+    #[case("exact:AAA", "AAAA", false)]
+    #[case("prefix:1", "11A", true)]
+    #[case("prefix:1", "21A", false)]
+    #[case("suffix:Z", "ZZZ", true)]
+    #[case("suffix:Z", "ZZA", false)]
+    #[case("regex:^.2.$", "122", true)]
+    #[case("regex:^.2.$", "1222", false)]
+    fn test_node_predicates(#[case] predicate: &str, #[case] node: &str, #[case] expected_result: bool)
+    {
+        let predicate = NodePredicate::from_str(predicate).unwrap();
+        assert_eq!(predicate.matches(node), expected_result);
+    }
+
+    #[rstest]
+    #[case("AAA")]
+    #[case("wat:AAA")]
+    #[case("regex:(")]
+    fn test_invalid_node_predicates(#[case] predicate: &str)
+    {
+        assert!(NodePredicate::from_str(predicate).is_err());
+    }
+
+    #[test]
+    fn test_long_labels_and_custom_alphabet()
+    {
+        let input = "ABCA
+
+START = (START, MIDDLE, START)
+MIDDLE = (START, START, FINISH)
+FINISH = (FINISH, FINISH, FINISH)";
+        let map = parse_input_with_alphabet(input, "ABC").unwrap();
+        assert_eq!(map.directions, vec![0, 1, 2, 0]);
+        let start = NodePredicate::Exact("START".to_string());
+        let goal = NodePredicate::Prefix("FIN".to_string());
+        assert_eq!(steps_to_goal(&map, &start, &goal), Ok(3));
     }
-}
\ No newline at end of file
+
+    #[rstest]
+    #[case("LR\n\nAAA = (BBB, CCC)", "LRX", "Node AAA has 2 connections but there are 3 directions")]
+    #[case("LRX\n\nAAA = (BBB, CCC)", "LR", "Direction X isn't in the alphabet LR")]
+    #[case("\n\nAAA = (BBB, CCC)", "LR", "No directions given")]
+    #[case("LR\n\nAAA = BBB", "LR", "Unable to parse node: AAA = BBB")]
+    #[case("LR\nAAA = (BBB, CCC)", "LR", "Expected directions and nodes separated by a blank line")]
+    fn test_invalid_maps(#[case] input: &str, #[case] alphabet: &str, #[case] expected_error: &str)
+    {
+        assert_eq!(parse_input_with_alphabet(input, alphabet).err().as_deref(), Some(expected_error));
+    }
+
+    #[test]
+    fn test_example_1_with_predicates()
+    {
+        let input = std::fs::read_to_string("inputs/day_8/example_1.txt")
+            .expect("Unable to read file")
+            .replace("\r\n", "\n");
+        let map = parse_input(&input);
+        let start = NodePredicate::from_str("regex:^A+$").unwrap();
+        let goal = NodePredicate::from_str("exact:ZZZ").unwrap();
+        assert_eq!(steps_to_goal(&map, &start, &goal), Ok(2));
+    }
+
+    fn parse_dot(dot: &str) -> (HashSet<String>, HashSet<(String, String, String)>)
+    {
+        let mut nodes = HashSet::new();
+        let mut edges = HashSet::new();
+        for line in dot.lines() {
+            if let Some((_full_match, from, to, label)) = regex_captures!(r#"^\s*"(\w+)" -> "(\w+)" \[label="(.)""#, line) {
+                edges.insert((from.to_string(), to.to_string(), label.to_string()));
+            } else if let Some((_full_match, node, _attributes)) = regex_captures!(r#"^\s*"(\w+)"( \[.*\])?;$"#, line) {
+                nodes.insert(node.to_string());
+            }
+        }
+        (nodes, edges)
+    }
+
+    #[rstest]
+    #[case("inputs/day_8/example_1.txt")]
+    #[case("inputs/day_8/example_2.txt")]
+    #[case("inputs/day_8/example_3.txt")]
+    fn test_dot_round_trip(#[case] file_name: &str)
+    {
+        let input = std::fs::read_to_string(file_name)
+            .expect("Unable to read file")
+            .replace("\r\n", "\n");
+        let map = parse_input(&input);
+        let dot = map.to_dot(&NodePredicate::Suffix("A".to_string()), &z_nodes(), None).unwrap();
+        let (nodes, edges) = parse_dot(&dot);
+
+        let expected_nodes: HashSet<_> = map.nodes.keys().cloned().collect();
+        let expected_edges: HashSet<_> = map.nodes.iter()
+            .flat_map(|(node, connections)| {
+                connections.targets.iter()
+                    .zip(["L", "R"])
+                    .map(|(target, label)| (node.clone(), target.clone(), label.to_string()))
+            })
+            .collect();
+        assert_eq!(nodes, expected_nodes);
+        assert_eq!(edges, expected_edges);
+    }
+
+    #[test]
+    fn test_dot_highlights()
+    {
+        let input = std::fs::read_to_string("inputs/day_8/example_3.txt")
+            .expect("Unable to read file")
+            .replace("\r\n", "\n");
+        let map = parse_input(&input);
+        let dot = map.to_dot(&NodePredicate::Suffix("A".to_string()), &z_nodes(), Some("11A")).unwrap();
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"11A\" [style=filled, fillcolor=palegreen];\n"));
+        assert!(dot.contains("    \"11Z\" [style=filled, fillcolor=lightcoral];\n"));
+        assert!(dot.contains("    \"11A\" -> \"11B\" [label=\"L\", color=blue, penwidth=2];\n"));
+        assert!(dot.contains("    \"11B\" -> \"11Z\" [label=\"R\", color=orange, penwidth=2];\n"));
+        assert!(dot.contains("    \"11Z\" -> \"11B\" [label=\"L\", color=orange, penwidth=2];\n"));
+        assert!(dot.contains("    \"22A\" -> \"22B\" [label=\"L\"];\n"));
+    }
+
+    #[rstest]
+    // ZZZ is only reachable from BBB, which AAA never reaches
+    #[case("L\n\nAAA = (CCC, BBB)\nBBB = (ZZZ, ZZZ)\nCCC = (AAA, AAA)\nZZZ = (ZZZ, ZZZ)", WalkError::Unreachable)]
+    #[case("L\n\nAAA = (BBB, BBB)\nBBB = (AAA, AAA)", WalkError::MissingNode("ZZZ".to_string()))]
+    #[case("L\n\nBBB = (ZZZ, ZZZ)\nZZZ = (ZZZ, ZZZ)", WalkError::MissingNode("AAA".to_string()))]
+    #[case("L\n\nAAA = (BBB, BBB)\nZZZ = (ZZZ, ZZZ)", WalkError::MissingNode("BBB".to_string()))]
+    fn test_part_one_errors(#[case] input: &str, #[case] expected_error: WalkError)
+    {
+        let map = parse_input(input);
+        let start = NodePredicate::Exact("AAA".to_string());
+        let goal = NodePredicate::Exact("ZZZ".to_string());
+        assert_eq!(steps_to_goal(&map, &start, &goal), Err(expected_error));
+    }
+
+    #[test]
+    fn test_part_two_no_start_nodes()
+    {
+        let map = parse_input("L\n\n11B = (11Z, 11Z)\n11Z = (11B, 11B)");
+        let start = NodePredicate::Suffix("A".to_string());
+        assert_eq!(steps_to_goal(&map, &start, &z_nodes()), Err(WalkError::NoStartNodes));
+    }
+}
@@ -2,33 +2,130 @@ use std::fs::File;
 use std::io::BufRead;
 use std::str::FromStr;
 use itertools::Itertools;
+use num::CheckedAdd;
+use num::rational::Ratio;
 
-fn determine_next_value(sequence: Vec<i64>) -> i64
-{
-    if sequence.iter().all_equal() {
-       return sequence[0];
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum SequenceError {
+    Empty,
+    // The differences only become constant once there's a single value left, so the data doesn't
+    // pin down a polynomial
+    NotPolynomial,
+    Overflow,
+}
+
+// Builds the difference table once, keeping just the first value of each row (Newton's forward
+// differences). The value at any index n is then sum(difference[k] * (n choose k)), which works
+// for indexes before the start and past the end of the sequence alike.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Extrapolator {
+    leading_differences: Vec<i128>,
+    len: usize,
+}
+
+impl Extrapolator {
+    // A single value is treated as a constant sequence
+    fn new(sequence: &[i64]) -> Result<Self, SequenceError>
+    {
+        if sequence.is_empty() {
+            return Err(SequenceError::Empty);
+        }
+        let mut row = sequence.iter().map(|value| *value as i128).collect_vec();
+        let mut leading_differences = Vec::new();
+        loop {
+            leading_differences.push(row[0]);
+            if row.len() == 1 && sequence.len() > 1 {
+                return Err(SequenceError::NotPolynomial);
+            }
+            if row.iter().all_equal() {
+                break;
+            }
+            row = row.iter()
+                .tuple_windows()
+                .map(|(a, b)| b.checked_sub(*a))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(SequenceError::Overflow)?;
+        }
+        Ok(Self {
+            leading_differences,
+            len: sequence.len(),
+        })
+    }
+
+    fn degree(&self) -> usize
+    {
+        self.leading_differences.len() - 1
+    }
+
+    // Value at an index into the sequence, which may be negative or past the end.
+    // None if it doesn't fit in an i128.
+    fn value_at(&self, index: i64) -> Option<i128>
+    {
+        let index = index as i128;
+        let mut binomial: i128 = 1;
+        let mut value: i128 = 0;
+        for (k, difference) in self.leading_differences.iter().enumerate() {
+            if k > 0 {
+                // n choose k from n choose k-1; always divides exactly
+                binomial = binomial.checked_mul(index - k as i128 + 1)? / k as i128;
+            }
+            value = value.checked_add(difference.checked_mul(binomial)?)?;
+        }
+        Some(value)
+    }
+
+    fn forwards(&self, steps: i64) -> Option<i128>
+    {
+        self.value_at(self.len as i64 - 1 + steps)
+    }
+
+    fn backwards(&self, steps: i64) -> Option<i128>
+    {
+        self.value_at(-steps)
     }
 
-    let next_sequence = sequence.iter()
-        .tuple_windows()
-        .map(|(a, b)| b - a)
-        .collect_vec();
-    let next_value_in_sequence_above = determine_next_value(next_sequence);
-    return sequence.last().unwrap() + next_value_in_sequence_above;
+    // Coefficients of the polynomial through the sequence, constant term first, where x is the
+    // index into the sequence. None if any intermediate value doesn't fit in an i128, which
+    // happens at a much lower degree than for value_at since k! grows so quickly.
+    fn coefficients(&self) -> Option<Vec<Ratio<i128>>>
+    {
+        let mut coefficients = vec![Ratio::from_integer(0); self.leading_differences.len()];
+        // x(x-1)...(x-k+1), constant term first
+        let mut falling_factorial: Vec<i128> = vec![1];
+        let mut factorial: i128 = 1;
+        for (k, difference) in self.leading_differences.iter().enumerate() {
+            if k > 0 {
+                factorial = factorial.checked_mul(k as i128)?;
+                let mut next = vec![0i128; falling_factorial.len() + 1];
+                for (power, coefficient) in falling_factorial.iter().enumerate() {
+                    next[power + 1] = next[power + 1].checked_add(*coefficient)?;
+                    next[power] = next[power].checked_sub(coefficient.checked_mul(k as i128 - 1)?)?;
+                }
+                falling_factorial = next;
+            }
+            for (power, coefficient) in falling_factorial.iter().enumerate() {
+                let term = Ratio::new(difference.checked_mul(*coefficient)?, factorial);
+                coefficients[power] = coefficients[power].checked_add(&term)?;
+            }
+        }
+        Some(coefficients)
+    }
 }
 
-fn determine_previous_value(sequence: Vec<i64>) -> i64
+fn determine_next_value(sequence: Vec<i64>) -> Result<i64, SequenceError>
 {
-    if sequence.iter().all_equal() {
-        return sequence[0];
-    }
+    let next_value = Extrapolator::new(&sequence)?
+        .forwards(1)
+        .ok_or(SequenceError::Overflow)?;
+    i64::try_from(next_value).map_err(|_| SequenceError::Overflow)
+}
 
-    let next_sequence = sequence.iter()
-        .tuple_windows()
-        .map(|(a, b)| b - a)
-        .collect_vec();
-    let previous_value_in_sequence_above = determine_previous_value(next_sequence);
-    return sequence.first().unwrap() - previous_value_in_sequence_above;
+fn determine_previous_value(sequence: Vec<i64>) -> Result<i64, SequenceError>
+{
+    let previous_value = Extrapolator::new(&sequence)?
+        .backwards(1)
+        .ok_or(SequenceError::Overflow)?;
+    i64::try_from(previous_value).map_err(|_| SequenceError::Overflow)
 }
 
 fn parse_line(line: &str) -> Vec<i64>
@@ -44,7 +141,8 @@ fn part_one(file_name: &str)
     let result: i64 = std::io::BufReader::new(file).lines()
         .map(|line| parse_line(&line.expect("Unable to read line")))
         .map(|sequence| determine_next_value(sequence))
-        .sum();
+        .sum::<Result<i64, _>>()
+        .expect("Unable to extrapolate sequence");
     println!("{}", result)
 }
 
@@ -54,7 +152,8 @@ fn part_two(file_name: &str)
     let result: i64 = std::io::BufReader::new(file).lines()
         .map(|line| parse_line(&line.expect("Unable to read line")))
         .map(|sequence| determine_previous_value(sequence))
-        .sum();
+        .sum::<Result<i64, _>>()
+        .expect("Unable to extrapolate sequence");
     println!("{}", result)
 }
 
@@ -89,7 +188,7 @@ mod test {
     #[case(vec![10, 13, 16, 21, 30, 45], 68)]
     fn test_determine_next_value(#[case] sequence: Vec<i64>, #[case] expected_result: i64)
     {
-        assert_eq!(determine_next_value(sequence), expected_result);
+        assert_eq!(determine_next_value(sequence), Ok(expected_result));
     }
 
     #[rstest]
@@ -98,6 +197,130 @@ mod test {
     #[case(vec![10, 13, 16, 21, 30, 45], 5)]
     fn test_determine_previous_value(#[case] sequence: Vec<i64>, #[case] expected_result: i64)
     {
-        assert_eq!(determine_previous_value(sequence), expected_result);
+        assert_eq!(determine_previous_value(sequence), Ok(expected_result));
+    }
+
+    #[rstest]
+    #[case(vec![0, 3, 6, 9, 12, 15], 1)]
+    #[case(vec![1, 3, 6, 10, 15, 21], 2)]
+    #[case(vec![10, 13, 16, 21, 30, 45], 3)]
+    #[case(vec![7, 7, 7], 0)]
+    fn test_degree(#[case] sequence: Vec<i64>, #[case] expected_degree: usize)
+    {
+        assert_eq!(Extrapolator::new(&sequence).unwrap().degree(), expected_degree);
+    }
+
+    #[rstest]
+    #[case(vec![1, 3, 6, 10, 15, 21], 3, Some(45))]
+    #[case(vec![1, 3, 6, 10, 15, 21], 100, Some(5671))]
+    #[case(vec![1, 3, 6, 10, 15, 21], -1, Some(0))]
+    #[case(vec![1, 3, 6, 10, 15, 21], -3, Some(1))]
+    #[case(vec![1, 4, 9, 16], 0, Some(16))]
+    #[case(vec![1, 4, 9, 16], -10, Some(81))]
+    fn test_extrapolate_steps(#[case] sequence: Vec<i64>, #[case] steps: i64, #[case] expected_value: Option<i128>)
+    {
+        let extrapolator = Extrapolator::new(&sequence).unwrap();
+        if steps >= 0 {
+            assert_eq!(extrapolator.forwards(steps), expected_value);
+        } else {
+            assert_eq!(extrapolator.backwards(-steps), expected_value);
+        }
+    }
+
+    #[test]
+    fn test_extrapolate_overflow()
+    {
+        let extrapolator = Extrapolator::new(&[0, 1, 8, 27, 64]).unwrap();
+        assert_eq!(extrapolator.forwards(i64::MAX / 2), None);
+    }
+
+    #[test]
+    fn test_value_at_matches_sequence()
+    {
+        let sequence = vec![10, 13, 16, 21, 30, 45];
+        let extrapolator = Extrapolator::new(&sequence).unwrap();
+        for (index, value) in sequence.iter().enumerate() {
+            assert_eq!(extrapolator.value_at(index as i64), Some(*value as i128));
+        }
+    }
+
+    #[rstest]
+    #[case(vec![0, 3, 6, 9, 12, 15], vec![(0, 1), (3, 1)])]
+    // Triangular numbers starting at 1: (x + 1)(x + 2) / 2
+    #[case(vec![1, 3, 6, 10, 15, 21], vec![(1, 1), (3, 2), (1, 2)])]
+    #[case(vec![10, 13, 16, 21, 30, 45], vec![(10, 1), (11, 3), (-1, 1), (1, 3)])]
+    fn test_coefficients(#[case] sequence: Vec<i64>, #[case] expected_coefficients: Vec<(i128, i128)>)
+    {
+        let expected_coefficients = expected_coefficients.into_iter()
+            .map(|(numerator, denominator)| Ratio::new(numerator, denominator))
+            .collect_vec();
+        assert_eq!(Extrapolator::new(&sequence).unwrap().coefficients(), Some(expected_coefficients));
+    }
+
+    #[test]
+    fn test_empty_sequence()
+    {
+        assert_eq!(Extrapolator::new(&[]), Err(SequenceError::Empty));
+        assert_eq!(determine_next_value(parse_line("")), Err(SequenceError::Empty));
+        assert_eq!(determine_previous_value(parse_line("   ")), Err(SequenceError::Empty));
+    }
+
+    #[rstest]
+    #[case(vec![5])]
+    #[case(vec![-3])]
+    #[case(vec![0])]
+    fn test_single_value_is_constant(#[case] sequence: Vec<i64>)
+    {
+        let extrapolator = Extrapolator::new(&sequence).unwrap();
+        assert_eq!(extrapolator.degree(), 0);
+        assert_eq!(determine_next_value(sequence.clone()), Ok(sequence[0]));
+        assert_eq!(determine_previous_value(sequence.clone()), Ok(sequence[0]));
+    }
+
+    #[rstest]
+    #[case(vec![1, 2])]
+    #[case(vec![1, 2, 4])]
+    #[case(vec![1, 2, 4, 8, 16, 32])]
+    fn test_not_polynomial(#[case] sequence: Vec<i64>)
+    {
+        assert_eq!(Extrapolator::new(&sequence), Err(SequenceError::NotPolynomial));
+        assert_eq!(determine_next_value(sequence), Err(SequenceError::NotPolynomial));
+    }
+
+    #[rstest]
+    #[case(vec![2, 2])]
+    #[case(vec![1, 2, 3])]
+    fn test_shortest_polynomials(#[case] sequence: Vec<i64>)
+    {
+        assert!(Extrapolator::new(&sequence).is_ok());
+    }
+
+    #[test]
+    fn test_next_value_overflow()
+    {
+        assert_eq!(determine_next_value(vec![i64::MAX - 2, i64::MAX - 1, i64::MAX]), Err(SequenceError::Overflow));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_coefficients_overflow()
+    {
+        // x choose 34, which value_at copes with, but the coefficients need 34! and that doesn't fit
+        // in an i128
+        let mut sequence = vec![0; 34];
+        sequence.extend([1, 35, 630]);
+        let extrapolator = Extrapolator::new(&sequence).unwrap();
+        assert_eq!(extrapolator.degree(), 34);
+        assert_eq!(extrapolator.forwards(1), Some(7770));
+        assert_eq!(extrapolator.coefficients(), None);
+    }
+
+    #[test]
+    fn test_difference_table_overflow()
+    {
+        // Each row of differences doubles in size, so these outgrow an i128 long before running out
+        let sequence = (0..70)
+            .map(|index| if index % 2 == 0 { i64::MAX } else { i64::MIN })
+            .collect_vec();
+        assert_eq!(Extrapolator::new(&sequence), Err(SequenceError::Overflow));
+    }
+}
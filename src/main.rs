@@ -12,6 +12,34 @@ mod day_11;
 mod util;
 mod day_12;
 
+const USAGE: &str = "Usage: advent-of-code-2023 <day> [arguments]
+
+  7 <input file> [--rules=part-one|part-two|aces-low|deuces-wild|poker] [--explain]
+  8 <input file> [--part=one|two] [--start=<predicate>] [--goal=<predicate>] [--alphabet=LR]
+      [--dot=<output file> [--highlight=<start node>]]
+      predicates are exact:<label>, prefix:<text>, suffix:<text> or regex:<pattern>
+  10 <input file> [--render=plain|ansi|upscaled] [--strategy=row-scan|flood-fill]
+  11 <input file> [--expansion=<factor>] [--rows=<factor>] [--columns=<factor>]
+      [nearest <x,y> | within <x,y> <distance> | closest <k> | farthest <k>]
+  12 <input file> [--unfold=<factor>] [--separator=?|#|.] [--deduce]";
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((day, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    };
+    let args = util::CliArgs::parse(args);
+    let result = match day.as_str() {
+        "7" => day_7::run(&args),
+        "8" => day_8::run(&args),
+        "10" => day_10::run(&args),
+        "11" => day_11::run(&args),
+        "12" => day_12::run(&args),
+        _ => Err(format!("No command line available for day {}", day)),
+    };
+    if let Err(error) = result {
+        eprintln!("{}\n\n{}", error, USAGE);
+        std::process::exit(1);
+    }
 }
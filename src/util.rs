@@ -33,4 +33,42 @@ impl std::ops::Sub for Point {
             y: self.y - rhs.y,
         }
     }
-}
\ No newline at end of file
+}
+// Command line arguments split into positionals, `--flag`s and `--option=value`s
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    positional: Vec<String>,
+    options: std::collections::HashMap<String, Option<String>>,
+}
+
+impl CliArgs {
+    pub fn parse(args: &[String]) -> Self {
+        let mut result = Self::default();
+        for arg in args {
+            match arg.strip_prefix("--") {
+                Some(option) => {
+                    let (name, value) = match option.split_once('=') {
+                        Some((name, value)) => (name, Some(value.to_string())),
+                        None => (option, None),
+                    };
+                    result.options.insert(name.to_string(), value);
+                }
+                None => result.positional.push(arg.clone()),
+            }
+        }
+        result
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(|arg| arg.as_str())
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name)
+            .and_then(|value| value.as_deref())
+    }
+}